@@ -1,8 +1,10 @@
+import json
 import subprocess
-import re
 import sys
 
-regex = re.compile(r"\[(?P<time>[\d.]*) (?P<unit>\w)s [\d.]* \ws [\d.]+ \ws\]")
+# random の分布での mysort だけを測る設定と、その results に書き出される計測結果
+CONFIG = "threshold.yaml"
+RESULTS = "threshold-results.json"
 
 print("threshold,small,medium,large")
 sys.stdout.flush()
@@ -17,17 +19,17 @@ for i in range(50, 150, 10):
     with open("mysort.c", "w") as f:
         f.write(f"#define INSERTION_SORT_THRESHOLD {i}\n" + code)
 
-    if subprocess.run(["zsh", "-c" , f"just bench"], stdout=subprocess.DEVNULL, stderr=subprocess.DEVNULL).returncode != 0:
+    if subprocess.run(["zsh", "-c" , f"just bench {CONFIG}"], stdout=subprocess.DEVNULL, stderr=subprocess.DEVNULL).returncode != 0:
         exit()
 
-    with open("./performance", "r") as f:
-        perf = f.read()
+    with open(RESULTS, "r") as f:
+        results = json.load(f)
 
-    times = [str(i)]
-    for time, unit in regex.findall(perf):
-        time = float(time)
-        if unit == "m":
-            time *= 1000.0
-        times.append(str(time))
+    # random の分布での mysort の平均時間 (µs) を小さい配列から順に並べる
+    mysort = sorted(
+        (r for r in results if r["group"] == "random" and r["algorithm"] == "mysort"),
+        key=lambda r: r["size"],
+    )
+    times = [str(i)] + [str(r["mean"] / 1000.0) for r in mysort]
     print(",".join(times))
     sys.stdout.flush()
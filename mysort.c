@@ -1,5 +1,7 @@
+#include <limits.h>
 #include <math.h>
 #include <stdbool.h>
+#include <stdlib.h>
 #include <string.h>
 
 #define MIN(i, j) (i > j ? j : i)
@@ -36,8 +38,651 @@ void insertion_sort(int *data, int len) {
     }
 }
 
+// ソーティングネットワークでソートする最大の要素数
+// 値は Rust 側の SORTING_NETWORK_MAX と一致させること
+#define SORTING_NETWORK_MAX 16
+
+// 分岐せずに data[i] <= data[j] にする (cmov 命令になる)
+#define COMPARE_EXCHANGE(data, i, j)   \
+    {                                  \
+        int a_ = (data)[i];            \
+        int b_ = (data)[j];            \
+        (data)[i] = a_ < b_ ? a_ : b_; \
+        (data)[j] = a_ < b_ ? b_ : a_; \
+    }
+
+// https://en.wikipedia.org/wiki/Sorting_network
+// https://bertdobbelaere.github.io/sorting_networks.html
+// 要素数ごとのソーティングネットワーク。
+// どの要素を比較交換するかは data の中身によらないので分岐予測を外さない。
+// 比較交換の回数は、13 入力が既知の最良 (45 回) より 1 回多い他は既知の最良と同じ。
+// 11 入力と 13〜15 入力は、大きいネットワークから末尾の入力に関わる比較交換を除いて作った。
+// 末尾の入力が INT_MAX なら比較交換で動かないので、除いても残りの入力はソートされる。
+
+// 2 入力、1 回の比較交換
+static inline void sorting_network_2(int *v) {
+    COMPARE_EXCHANGE(v, 0, 1)
+}
+
+// 3 入力、3 回の比較交換
+static inline void sorting_network_3(int *v) {
+    COMPARE_EXCHANGE(v, 0, 2)
+
+    COMPARE_EXCHANGE(v, 0, 1)
+
+    COMPARE_EXCHANGE(v, 1, 2)
+}
+
+// 4 入力、5 回の比較交換
+static inline void sorting_network_4(int *v) {
+    COMPARE_EXCHANGE(v, 0, 2)
+    COMPARE_EXCHANGE(v, 1, 3)
+
+    COMPARE_EXCHANGE(v, 0, 1)
+    COMPARE_EXCHANGE(v, 2, 3)
+
+    COMPARE_EXCHANGE(v, 1, 2)
+}
+
+// 5 入力、9 回の比較交換
+static inline void sorting_network_5(int *v) {
+    COMPARE_EXCHANGE(v, 0, 3)
+    COMPARE_EXCHANGE(v, 1, 4)
+
+    COMPARE_EXCHANGE(v, 0, 2)
+    COMPARE_EXCHANGE(v, 1, 3)
+
+    COMPARE_EXCHANGE(v, 0, 1)
+    COMPARE_EXCHANGE(v, 2, 4)
+
+    COMPARE_EXCHANGE(v, 1, 2)
+    COMPARE_EXCHANGE(v, 3, 4)
+
+    COMPARE_EXCHANGE(v, 2, 3)
+}
+
+// 6 入力、12 回の比較交換
+static inline void sorting_network_6(int *v) {
+    COMPARE_EXCHANGE(v, 0, 5)
+    COMPARE_EXCHANGE(v, 1, 3)
+    COMPARE_EXCHANGE(v, 2, 4)
+
+    COMPARE_EXCHANGE(v, 1, 2)
+    COMPARE_EXCHANGE(v, 3, 4)
+
+    COMPARE_EXCHANGE(v, 0, 3)
+    COMPARE_EXCHANGE(v, 2, 5)
+
+    COMPARE_EXCHANGE(v, 0, 1)
+    COMPARE_EXCHANGE(v, 2, 3)
+    COMPARE_EXCHANGE(v, 4, 5)
+
+    COMPARE_EXCHANGE(v, 1, 2)
+    COMPARE_EXCHANGE(v, 3, 4)
+}
+
+// 7 入力、16 回の比較交換
+static inline void sorting_network_7(int *v) {
+    COMPARE_EXCHANGE(v, 0, 6)
+    COMPARE_EXCHANGE(v, 2, 3)
+    COMPARE_EXCHANGE(v, 4, 5)
+
+    COMPARE_EXCHANGE(v, 0, 2)
+    COMPARE_EXCHANGE(v, 1, 4)
+    COMPARE_EXCHANGE(v, 3, 6)
+
+    COMPARE_EXCHANGE(v, 0, 1)
+    COMPARE_EXCHANGE(v, 2, 5)
+    COMPARE_EXCHANGE(v, 3, 4)
+
+    COMPARE_EXCHANGE(v, 1, 2)
+    COMPARE_EXCHANGE(v, 4, 6)
+
+    COMPARE_EXCHANGE(v, 2, 3)
+    COMPARE_EXCHANGE(v, 4, 5)
+
+    COMPARE_EXCHANGE(v, 1, 2)
+    COMPARE_EXCHANGE(v, 3, 4)
+    COMPARE_EXCHANGE(v, 5, 6)
+}
+
+// 8 入力、19 回の比較交換
+static inline void sorting_network_8(int *v) {
+    COMPARE_EXCHANGE(v, 0, 2)
+    COMPARE_EXCHANGE(v, 1, 3)
+    COMPARE_EXCHANGE(v, 4, 6)
+    COMPARE_EXCHANGE(v, 5, 7)
+
+    COMPARE_EXCHANGE(v, 0, 4)
+    COMPARE_EXCHANGE(v, 1, 5)
+    COMPARE_EXCHANGE(v, 2, 6)
+    COMPARE_EXCHANGE(v, 3, 7)
+
+    COMPARE_EXCHANGE(v, 0, 1)
+    COMPARE_EXCHANGE(v, 2, 3)
+    COMPARE_EXCHANGE(v, 4, 5)
+    COMPARE_EXCHANGE(v, 6, 7)
+
+    COMPARE_EXCHANGE(v, 2, 4)
+    COMPARE_EXCHANGE(v, 3, 5)
+
+    COMPARE_EXCHANGE(v, 1, 4)
+    COMPARE_EXCHANGE(v, 3, 6)
+
+    COMPARE_EXCHANGE(v, 1, 2)
+    COMPARE_EXCHANGE(v, 3, 4)
+    COMPARE_EXCHANGE(v, 5, 6)
+}
+
+// 9 入力、25 回の比較交換
+static inline void sorting_network_9(int *v) {
+    COMPARE_EXCHANGE(v, 0, 3)
+    COMPARE_EXCHANGE(v, 1, 7)
+    COMPARE_EXCHANGE(v, 2, 5)
+    COMPARE_EXCHANGE(v, 4, 8)
+
+    COMPARE_EXCHANGE(v, 0, 7)
+    COMPARE_EXCHANGE(v, 2, 4)
+    COMPARE_EXCHANGE(v, 3, 8)
+    COMPARE_EXCHANGE(v, 5, 6)
+
+    COMPARE_EXCHANGE(v, 0, 2)
+    COMPARE_EXCHANGE(v, 1, 3)
+    COMPARE_EXCHANGE(v, 4, 5)
+    COMPARE_EXCHANGE(v, 7, 8)
+
+    COMPARE_EXCHANGE(v, 1, 4)
+    COMPARE_EXCHANGE(v, 3, 6)
+    COMPARE_EXCHANGE(v, 5, 7)
+
+    COMPARE_EXCHANGE(v, 0, 1)
+    COMPARE_EXCHANGE(v, 2, 4)
+    COMPARE_EXCHANGE(v, 3, 5)
+    COMPARE_EXCHANGE(v, 6, 8)
+
+    COMPARE_EXCHANGE(v, 2, 3)
+    COMPARE_EXCHANGE(v, 4, 5)
+    COMPARE_EXCHANGE(v, 6, 7)
+
+    COMPARE_EXCHANGE(v, 1, 2)
+    COMPARE_EXCHANGE(v, 3, 4)
+    COMPARE_EXCHANGE(v, 5, 6)
+}
+
+// 10 入力、29 回の比較交換
+static inline void sorting_network_10(int *v) {
+    COMPARE_EXCHANGE(v, 0, 8)
+    COMPARE_EXCHANGE(v, 1, 9)
+    COMPARE_EXCHANGE(v, 2, 7)
+    COMPARE_EXCHANGE(v, 3, 5)
+    COMPARE_EXCHANGE(v, 4, 6)
+
+    COMPARE_EXCHANGE(v, 0, 2)
+    COMPARE_EXCHANGE(v, 1, 4)
+    COMPARE_EXCHANGE(v, 5, 8)
+    COMPARE_EXCHANGE(v, 7, 9)
+
+    COMPARE_EXCHANGE(v, 0, 3)
+    COMPARE_EXCHANGE(v, 2, 4)
+    COMPARE_EXCHANGE(v, 5, 7)
+    COMPARE_EXCHANGE(v, 6, 9)
+
+    COMPARE_EXCHANGE(v, 0, 1)
+    COMPARE_EXCHANGE(v, 3, 6)
+    COMPARE_EXCHANGE(v, 8, 9)
+
+    COMPARE_EXCHANGE(v, 1, 5)
+    COMPARE_EXCHANGE(v, 2, 3)
+    COMPARE_EXCHANGE(v, 4, 8)
+    COMPARE_EXCHANGE(v, 6, 7)
+
+    COMPARE_EXCHANGE(v, 1, 2)
+    COMPARE_EXCHANGE(v, 3, 5)
+    COMPARE_EXCHANGE(v, 4, 6)
+    COMPARE_EXCHANGE(v, 7, 8)
+
+    COMPARE_EXCHANGE(v, 2, 3)
+    COMPARE_EXCHANGE(v, 4, 5)
+    COMPARE_EXCHANGE(v, 6, 7)
+
+    COMPARE_EXCHANGE(v, 3, 4)
+    COMPARE_EXCHANGE(v, 5, 6)
+}
+
+// 11 入力、35 回の比較交換。12 入力から作った
+static inline void sorting_network_11(int *v) {
+    COMPARE_EXCHANGE(v, 0, 8)
+    COMPARE_EXCHANGE(v, 1, 7)
+    COMPARE_EXCHANGE(v, 2, 6)
+    COMPARE_EXCHANGE(v, 4, 10)
+    COMPARE_EXCHANGE(v, 5, 9)
+
+    COMPARE_EXCHANGE(v, 0, 1)
+    COMPARE_EXCHANGE(v, 2, 5)
+    COMPARE_EXCHANGE(v, 3, 4)
+    COMPARE_EXCHANGE(v, 6, 9)
+    COMPARE_EXCHANGE(v, 7, 8)
+
+    COMPARE_EXCHANGE(v, 0, 2)
+    COMPARE_EXCHANGE(v, 1, 6)
+    COMPARE_EXCHANGE(v, 5, 10)
+
+    COMPARE_EXCHANGE(v, 0, 3)
+    COMPARE_EXCHANGE(v, 1, 2)
+    COMPARE_EXCHANGE(v, 4, 6)
+    COMPARE_EXCHANGE(v, 5, 7)
+    COMPARE_EXCHANGE(v, 9, 10)
+
+    COMPARE_EXCHANGE(v, 1, 4)
+    COMPARE_EXCHANGE(v, 3, 5)
+    COMPARE_EXCHANGE(v, 6, 8)
+    COMPARE_EXCHANGE(v, 7, 10)
+
+    COMPARE_EXCHANGE(v, 1, 3)
+    COMPARE_EXCHANGE(v, 2, 5)
+    COMPARE_EXCHANGE(v, 6, 9)
+    COMPARE_EXCHANGE(v, 8, 10)
+
+    COMPARE_EXCHANGE(v, 2, 3)
+    COMPARE_EXCHANGE(v, 4, 5)
+    COMPARE_EXCHANGE(v, 6, 7)
+    COMPARE_EXCHANGE(v, 8, 9)
+
+    COMPARE_EXCHANGE(v, 4, 6)
+    COMPARE_EXCHANGE(v, 5, 7)
+
+    COMPARE_EXCHANGE(v, 3, 4)
+    COMPARE_EXCHANGE(v, 5, 6)
+    COMPARE_EXCHANGE(v, 7, 8)
+}
+
+// 12 入力、39 回の比較交換
+static inline void sorting_network_12(int *v) {
+    COMPARE_EXCHANGE(v, 0, 8)
+    COMPARE_EXCHANGE(v, 1, 7)
+    COMPARE_EXCHANGE(v, 2, 6)
+    COMPARE_EXCHANGE(v, 3, 11)
+    COMPARE_EXCHANGE(v, 4, 10)
+    COMPARE_EXCHANGE(v, 5, 9)
+
+    COMPARE_EXCHANGE(v, 0, 1)
+    COMPARE_EXCHANGE(v, 2, 5)
+    COMPARE_EXCHANGE(v, 3, 4)
+    COMPARE_EXCHANGE(v, 6, 9)
+    COMPARE_EXCHANGE(v, 7, 8)
+    COMPARE_EXCHANGE(v, 10, 11)
+
+    COMPARE_EXCHANGE(v, 0, 2)
+    COMPARE_EXCHANGE(v, 1, 6)
+    COMPARE_EXCHANGE(v, 5, 10)
+    COMPARE_EXCHANGE(v, 9, 11)
+
+    COMPARE_EXCHANGE(v, 0, 3)
+    COMPARE_EXCHANGE(v, 1, 2)
+    COMPARE_EXCHANGE(v, 4, 6)
+    COMPARE_EXCHANGE(v, 5, 7)
+    COMPARE_EXCHANGE(v, 8, 11)
+    COMPARE_EXCHANGE(v, 9, 10)
+
+    COMPARE_EXCHANGE(v, 1, 4)
+    COMPARE_EXCHANGE(v, 3, 5)
+    COMPARE_EXCHANGE(v, 6, 8)
+    COMPARE_EXCHANGE(v, 7, 10)
+
+    COMPARE_EXCHANGE(v, 1, 3)
+    COMPARE_EXCHANGE(v, 2, 5)
+    COMPARE_EXCHANGE(v, 6, 9)
+    COMPARE_EXCHANGE(v, 8, 10)
+
+    COMPARE_EXCHANGE(v, 2, 3)
+    COMPARE_EXCHANGE(v, 4, 5)
+    COMPARE_EXCHANGE(v, 6, 7)
+    COMPARE_EXCHANGE(v, 8, 9)
+
+    COMPARE_EXCHANGE(v, 4, 6)
+    COMPARE_EXCHANGE(v, 5, 7)
+
+    COMPARE_EXCHANGE(v, 3, 4)
+    COMPARE_EXCHANGE(v, 5, 6)
+    COMPARE_EXCHANGE(v, 7, 8)
+}
+
+// 13 入力、46 回の比較交換。16 入力から作った
+static inline void sorting_network_13(int *v) {
+    COMPARE_EXCHANGE(v, 1, 12)
+    COMPARE_EXCHANGE(v, 4, 8)
+    COMPARE_EXCHANGE(v, 5, 6)
+    COMPARE_EXCHANGE(v, 7, 11)
+    COMPARE_EXCHANGE(v, 9, 10)
+
+    COMPARE_EXCHANGE(v, 0, 5)
+    COMPARE_EXCHANGE(v, 1, 7)
+    COMPARE_EXCHANGE(v, 2, 9)
+    COMPARE_EXCHANGE(v, 3, 4)
+    COMPARE_EXCHANGE(v, 11, 12)
+
+    COMPARE_EXCHANGE(v, 0, 1)
+    COMPARE_EXCHANGE(v, 2, 3)
+    COMPARE_EXCHANGE(v, 4, 5)
+    COMPARE_EXCHANGE(v, 6, 8)
+    COMPARE_EXCHANGE(v, 7, 9)
+    COMPARE_EXCHANGE(v, 10, 11)
+
+    COMPARE_EXCHANGE(v, 0, 2)
+    COMPARE_EXCHANGE(v, 1, 3)
+    COMPARE_EXCHANGE(v, 4, 10)
+    COMPARE_EXCHANGE(v, 5, 11)
+    COMPARE_EXCHANGE(v, 6, 7)
+    COMPARE_EXCHANGE(v, 8, 9)
+
+    COMPARE_EXCHANGE(v, 1, 2)
+    COMPARE_EXCHANGE(v, 3, 12)
+    COMPARE_EXCHANGE(v, 4, 6)
+    COMPARE_EXCHANGE(v, 5, 7)
+    COMPARE_EXCHANGE(v, 8, 10)
+    COMPARE_EXCHANGE(v, 9, 11)
+
+    COMPARE_EXCHANGE(v, 1, 4)
+    COMPARE_EXCHANGE(v, 2, 6)
+    COMPARE_EXCHANGE(v, 5, 8)
+    COMPARE_EXCHANGE(v, 7, 10)
+
+    COMPARE_EXCHANGE(v, 2, 4)
+    COMPARE_EXCHANGE(v, 3, 6)
+    COMPARE_EXCHANGE(v, 9, 12)
+
+    COMPARE_EXCHANGE(v, 3, 5)
+    COMPARE_EXCHANGE(v, 6, 8)
+    COMPARE_EXCHANGE(v, 7, 9)
+    COMPARE_EXCHANGE(v, 10, 12)
+
+    COMPARE_EXCHANGE(v, 3, 4)
+    COMPARE_EXCHANGE(v, 5, 6)
+    COMPARE_EXCHANGE(v, 7, 8)
+    COMPARE_EXCHANGE(v, 9, 10)
+    COMPARE_EXCHANGE(v, 11, 12)
+
+    COMPARE_EXCHANGE(v, 6, 7)
+    COMPARE_EXCHANGE(v, 8, 9)
+}
+
+// 14 入力、51 回の比較交換。16 入力から作った
+static inline void sorting_network_14(int *v) {
+    COMPARE_EXCHANGE(v, 0, 13)
+    COMPARE_EXCHANGE(v, 1, 12)
+    COMPARE_EXCHANGE(v, 4, 8)
+    COMPARE_EXCHANGE(v, 5, 6)
+    COMPARE_EXCHANGE(v, 7, 11)
+    COMPARE_EXCHANGE(v, 9, 10)
+
+    COMPARE_EXCHANGE(v, 0, 5)
+    COMPARE_EXCHANGE(v, 1, 7)
+    COMPARE_EXCHANGE(v, 2, 9)
+    COMPARE_EXCHANGE(v, 3, 4)
+    COMPARE_EXCHANGE(v, 6, 13)
+    COMPARE_EXCHANGE(v, 11, 12)
+
+    COMPARE_EXCHANGE(v, 0, 1)
+    COMPARE_EXCHANGE(v, 2, 3)
+    COMPARE_EXCHANGE(v, 4, 5)
+    COMPARE_EXCHANGE(v, 6, 8)
+    COMPARE_EXCHANGE(v, 7, 9)
+    COMPARE_EXCHANGE(v, 10, 11)
+    COMPARE_EXCHANGE(v, 12, 13)
+
+    COMPARE_EXCHANGE(v, 0, 2)
+    COMPARE_EXCHANGE(v, 1, 3)
+    COMPARE_EXCHANGE(v, 4, 10)
+    COMPARE_EXCHANGE(v, 5, 11)
+    COMPARE_EXCHANGE(v, 6, 7)
+    COMPARE_EXCHANGE(v, 8, 9)
+
+    COMPARE_EXCHANGE(v, 1, 2)
+    COMPARE_EXCHANGE(v, 3, 12)
+    COMPARE_EXCHANGE(v, 4, 6)
+    COMPARE_EXCHANGE(v, 5, 7)
+    COMPARE_EXCHANGE(v, 8, 10)
+    COMPARE_EXCHANGE(v, 9, 11)
+
+    COMPARE_EXCHANGE(v, 1, 4)
+    COMPARE_EXCHANGE(v, 2, 6)
+    COMPARE_EXCHANGE(v, 5, 8)
+    COMPARE_EXCHANGE(v, 7, 10)
+    COMPARE_EXCHANGE(v, 9, 13)
+
+    COMPARE_EXCHANGE(v, 2, 4)
+    COMPARE_EXCHANGE(v, 3, 6)
+    COMPARE_EXCHANGE(v, 9, 12)
+    COMPARE_EXCHANGE(v, 11, 13)
+
+    COMPARE_EXCHANGE(v, 3, 5)
+    COMPARE_EXCHANGE(v, 6, 8)
+    COMPARE_EXCHANGE(v, 7, 9)
+    COMPARE_EXCHANGE(v, 10, 12)
+
+    COMPARE_EXCHANGE(v, 3, 4)
+    COMPARE_EXCHANGE(v, 5, 6)
+    COMPARE_EXCHANGE(v, 7, 8)
+    COMPARE_EXCHANGE(v, 9, 10)
+    COMPARE_EXCHANGE(v, 11, 12)
+
+    COMPARE_EXCHANGE(v, 6, 7)
+    COMPARE_EXCHANGE(v, 8, 9)
+}
+
+// 15 入力、56 回の比較交換。16 入力から作った
+static inline void sorting_network_15(int *v) {
+    COMPARE_EXCHANGE(v, 0, 13)
+    COMPARE_EXCHANGE(v, 1, 12)
+    COMPARE_EXCHANGE(v, 3, 14)
+    COMPARE_EXCHANGE(v, 4, 8)
+    COMPARE_EXCHANGE(v, 5, 6)
+    COMPARE_EXCHANGE(v, 7, 11)
+    COMPARE_EXCHANGE(v, 9, 10)
+
+    COMPARE_EXCHANGE(v, 0, 5)
+    COMPARE_EXCHANGE(v, 1, 7)
+    COMPARE_EXCHANGE(v, 2, 9)
+    COMPARE_EXCHANGE(v, 3, 4)
+    COMPARE_EXCHANGE(v, 6, 13)
+    COMPARE_EXCHANGE(v, 8, 14)
+    COMPARE_EXCHANGE(v, 11, 12)
+
+    COMPARE_EXCHANGE(v, 0, 1)
+    COMPARE_EXCHANGE(v, 2, 3)
+    COMPARE_EXCHANGE(v, 4, 5)
+    COMPARE_EXCHANGE(v, 6, 8)
+    COMPARE_EXCHANGE(v, 7, 9)
+    COMPARE_EXCHANGE(v, 10, 11)
+    COMPARE_EXCHANGE(v, 12, 13)
+
+    COMPARE_EXCHANGE(v, 0, 2)
+    COMPARE_EXCHANGE(v, 1, 3)
+    COMPARE_EXCHANGE(v, 4, 10)
+    COMPARE_EXCHANGE(v, 5, 11)
+    COMPARE_EXCHANGE(v, 6, 7)
+    COMPARE_EXCHANGE(v, 8, 9)
+    COMPARE_EXCHANGE(v, 12, 14)
+
+    COMPARE_EXCHANGE(v, 1, 2)
+    COMPARE_EXCHANGE(v, 3, 12)
+    COMPARE_EXCHANGE(v, 4, 6)
+    COMPARE_EXCHANGE(v, 5, 7)
+    COMPARE_EXCHANGE(v, 8, 10)
+    COMPARE_EXCHANGE(v, 9, 11)
+    COMPARE_EXCHANGE(v, 13, 14)
+
+    COMPARE_EXCHANGE(v, 1, 4)
+    COMPARE_EXCHANGE(v, 2, 6)
+    COMPARE_EXCHANGE(v, 5, 8)
+    COMPARE_EXCHANGE(v, 7, 10)
+    COMPARE_EXCHANGE(v, 9, 13)
+    COMPARE_EXCHANGE(v, 11, 14)
+
+    COMPARE_EXCHANGE(v, 2, 4)
+    COMPARE_EXCHANGE(v, 3, 6)
+    COMPARE_EXCHANGE(v, 9, 12)
+    COMPARE_EXCHANGE(v, 11, 13)
+
+    COMPARE_EXCHANGE(v, 3, 5)
+    COMPARE_EXCHANGE(v, 6, 8)
+    COMPARE_EXCHANGE(v, 7, 9)
+    COMPARE_EXCHANGE(v, 10, 12)
+
+    COMPARE_EXCHANGE(v, 3, 4)
+    COMPARE_EXCHANGE(v, 5, 6)
+    COMPARE_EXCHANGE(v, 7, 8)
+    COMPARE_EXCHANGE(v, 9, 10)
+    COMPARE_EXCHANGE(v, 11, 12)
+
+    COMPARE_EXCHANGE(v, 6, 7)
+    COMPARE_EXCHANGE(v, 8, 9)
+}
+
+// 16 入力、60 回の比較交換
+static inline void sorting_network_16(int *v) {
+    COMPARE_EXCHANGE(v, 0, 13)
+    COMPARE_EXCHANGE(v, 1, 12)
+    COMPARE_EXCHANGE(v, 2, 15)
+    COMPARE_EXCHANGE(v, 3, 14)
+    COMPARE_EXCHANGE(v, 4, 8)
+    COMPARE_EXCHANGE(v, 5, 6)
+    COMPARE_EXCHANGE(v, 7, 11)
+    COMPARE_EXCHANGE(v, 9, 10)
+
+    COMPARE_EXCHANGE(v, 0, 5)
+    COMPARE_EXCHANGE(v, 1, 7)
+    COMPARE_EXCHANGE(v, 2, 9)
+    COMPARE_EXCHANGE(v, 3, 4)
+    COMPARE_EXCHANGE(v, 6, 13)
+    COMPARE_EXCHANGE(v, 8, 14)
+    COMPARE_EXCHANGE(v, 10, 15)
+    COMPARE_EXCHANGE(v, 11, 12)
+
+    COMPARE_EXCHANGE(v, 0, 1)
+    COMPARE_EXCHANGE(v, 2, 3)
+    COMPARE_EXCHANGE(v, 4, 5)
+    COMPARE_EXCHANGE(v, 6, 8)
+    COMPARE_EXCHANGE(v, 7, 9)
+    COMPARE_EXCHANGE(v, 10, 11)
+    COMPARE_EXCHANGE(v, 12, 13)
+    COMPARE_EXCHANGE(v, 14, 15)
+
+    COMPARE_EXCHANGE(v, 0, 2)
+    COMPARE_EXCHANGE(v, 1, 3)
+    COMPARE_EXCHANGE(v, 4, 10)
+    COMPARE_EXCHANGE(v, 5, 11)
+    COMPARE_EXCHANGE(v, 6, 7)
+    COMPARE_EXCHANGE(v, 8, 9)
+    COMPARE_EXCHANGE(v, 12, 14)
+    COMPARE_EXCHANGE(v, 13, 15)
+
+    COMPARE_EXCHANGE(v, 1, 2)
+    COMPARE_EXCHANGE(v, 3, 12)
+    COMPARE_EXCHANGE(v, 4, 6)
+    COMPARE_EXCHANGE(v, 5, 7)
+    COMPARE_EXCHANGE(v, 8, 10)
+    COMPARE_EXCHANGE(v, 9, 11)
+    COMPARE_EXCHANGE(v, 13, 14)
+
+    COMPARE_EXCHANGE(v, 1, 4)
+    COMPARE_EXCHANGE(v, 2, 6)
+    COMPARE_EXCHANGE(v, 5, 8)
+    COMPARE_EXCHANGE(v, 7, 10)
+    COMPARE_EXCHANGE(v, 9, 13)
+    COMPARE_EXCHANGE(v, 11, 14)
+
+    COMPARE_EXCHANGE(v, 2, 4)
+    COMPARE_EXCHANGE(v, 3, 6)
+    COMPARE_EXCHANGE(v, 9, 12)
+    COMPARE_EXCHANGE(v, 11, 13)
+
+    COMPARE_EXCHANGE(v, 3, 5)
+    COMPARE_EXCHANGE(v, 6, 8)
+    COMPARE_EXCHANGE(v, 7, 9)
+    COMPARE_EXCHANGE(v, 10, 12)
+
+    COMPARE_EXCHANGE(v, 3, 4)
+    COMPARE_EXCHANGE(v, 5, 6)
+    COMPARE_EXCHANGE(v, 7, 8)
+    COMPARE_EXCHANGE(v, 9, 10)
+    COMPARE_EXCHANGE(v, 11, 12)
+
+    COMPARE_EXCHANGE(v, 6, 7)
+    COMPARE_EXCHANGE(v, 8, 9)
+}
+
+// 要素数に合わせたネットワークでソートする
+// 制約: len <= SORTING_NETWORK_MAX
+void sorting_network(int *data, int len) {
+    switch (len) {
+        case 2:
+            sorting_network_2(data);
+            break;
+        case 3:
+            sorting_network_3(data);
+            break;
+        case 4:
+            sorting_network_4(data);
+            break;
+        case 5:
+            sorting_network_5(data);
+            break;
+        case 6:
+            sorting_network_6(data);
+            break;
+        case 7:
+            sorting_network_7(data);
+            break;
+        case 8:
+            sorting_network_8(data);
+            break;
+        case 9:
+            sorting_network_9(data);
+            break;
+        case 10:
+            sorting_network_10(data);
+            break;
+        case 11:
+            sorting_network_11(data);
+            break;
+        case 12:
+            sorting_network_12(data);
+            break;
+        case 13:
+            sorting_network_13(data);
+            break;
+        case 14:
+            sorting_network_14(data);
+            break;
+        case 15:
+            sorting_network_15(data);
+            break;
+        case 16:
+            sorting_network_16(data);
+            break;
+        default:
+            // 0 要素と 1 要素は何もしなくてよい
+            break;
+    }
+}
+
+// 小さい配列向けのソート
+// SORTING_NETWORK_MAX 要素ずつソーティングネットワークでソートしてから、
+// 整列済みのブロックを挿入ソートでまとめる。
+void small_sort(int *data, int len) {
+    if (len <= SORTING_NETWORK_MAX) {
+        sorting_network(data, len);
+        return;
+    }
+    for (int i = 0; i < len; i += SORTING_NETWORK_MAX) {
+        sorting_network(data + i, MIN(SORTING_NETWORK_MAX, len - i));
+    }
+    insertion_sort(data, len);
+}
+
 // node (index) 以下のノードが heap property を満たすようにノードを入れ替える
-static inline void down_heap(int *data, int len, int node) {
+void down_heap(int *data, int len, int node) {
     while (true) {
         int child = 2 * node + 1;
         if (child >= len) {
@@ -74,7 +719,7 @@ void heapsort(int *data, int len) {
 
 // Block Quicksort Partition
 // https://drops.dagstuhl.de/opus/volltexte/2016/6389/pdf/LIPIcs-ESA-2016-38.pdf
-static inline int block_partition(int *data, int len, int pivot) {
+int block_partition(int *data, int len, int pivot) {
     int l_offsets[PARTITION_BLOCK];
     int r_offsets[PARTITION_BLOCK];
     int l_start = 0;
@@ -165,11 +810,44 @@ tiny:
 }
 
 // ベンチマークにより最適な値を決定
+// 値は Rust 側の INSERTION_SORT_THRESHOLD と一致させること
 #define INSERTION_SORT_THRESHOLD 55
 
+// https://en.wikipedia.org/wiki/Dutch_national_flag_problem
+// pivot より小さい要素、等しい要素、大きい要素の 3 つに分割する。
+// 分割後は [0, *lt) < pivot, [*lt, *gt) == pivot, [*gt, len) > pivot となる。
+// 重複の多い配列では、pivot と等しい要素の塊を以降の再帰から外せる。
+void three_way_partition(int *data, int len, int pivot, int *lt, int *gt) {
+    int l = 0;
+    int i = 0;
+    int g = len;
+    while (i < g) {
+        if (data[i] < pivot) {
+            SWAP(data, l, i);
+            l += 1;
+            i += 1;
+        } else if (data[i] > pivot) {
+            g -= 1;
+            SWAP(data, i, g);
+        } else {
+            i += 1;
+        }
+    }
+    *lt = l;
+    *gt = g;
+}
+
+// introsort の分割方法
+// 値は Rust 側の PartitionScheme と一致させること
+enum partition_scheme {
+    PARTITION_SCHEME_BLOCK = 0,
+    PARTITION_SCHEME_THREE_WAY = 1,
+};
+
 // https://en.wikipedia.org/wiki/Quicksort
 // https://en.wikipedia.org/wiki/Introsort
-static inline void introsort_internal(int *data, int len, int recur_limit) {
+static inline void introsort_internal(int *data, int len, int recur_limit,
+                                      enum partition_scheme scheme) {
     // select 7 as pivot
     // 8 4 3 7 6 5 2 1
     // ↑ HI          ↑ LO  swap
@@ -202,17 +880,337 @@ static inline void introsort_internal(int *data, int len, int recur_limit) {
     int pivot_index = len / 2;
     int pivot = data[pivot_index];
 
+    if (scheme == PARTITION_SCHEME_THREE_WAY) {
+        int lt, gt;
+        three_way_partition(data, len, pivot, &lt, &gt);
+
+        // pivot と等しい要素はすでに最終的な位置にあるので再帰しない
+        introsort_internal(data, lt, recur_limit - 1, scheme);
+        introsort_internal(data + gt, len - gt, recur_limit - 1, scheme);
+        return;
+    }
+
     int partition = block_partition(data, len, pivot);
 
-    introsort_internal(data, partition, recur_limit - 1);
-    introsort_internal(data + partition, len - partition, recur_limit - 1);
+    introsort_internal(data, partition, recur_limit - 1, scheme);
+    introsort_internal(data + partition, len - partition, recur_limit - 1,
+                       scheme);
 }
 
 void introsort(int *data, int len) {
-    introsort_internal(data, len, log2(len) * 2);
+    // log2(0) は -inf で、int に変換すると未定義動作になる
+    if (len <= 1) {
+        return;
+    }
+    introsort_internal(data, len, log2(len) * 2, PARTITION_SCHEME_BLOCK);
+}
+
+void introsort_with_partition(int *data, int len,
+                              enum partition_scheme scheme) {
+    if (len <= 1) {
+        return;
+    }
+    introsort_internal(data, len, log2(len) * 2, scheme);
+}
+
+// https://en.wikipedia.org/wiki/Quicksort#Multi-pivot_quicksort
+// https://codeblab.com/wp-content/uploads/2009/09/DualPivotQuicksort.pdf
+// Yaroslavskiy の Dual-Pivot Quicksort
+// 2 つの pivot p <= q で < p, p <= && <= q, > q の 3 つに分割する。
+// 挿入ソートと heapsort への切り替えは introsort_internal と同じ。
+static void dual_pivot_quicksort_internal(int *data, int len,
+                                          int recur_limit) {
+    if (len <= 1) {
+        return;
+    }
+
+    if (len < INSERTION_SORT_THRESHOLD) {
+        insertion_sort(data, len);
+        return;
+    }
+
+    if (recur_limit == 0) {
+        heapsort(data, len);
+        return;
+    }
+
+    // 1/3 と 2/3 の位置の要素を pivot として両端に置く
+    int third = len / 3;
+    SWAP(data, 0, third);
+    SWAP(data, len - 1, len - 1 - third);
+    if (data[0] > data[len - 1]) {
+        SWAP(data, 0, len - 1);
+    }
+    int p = data[0];
+    int q = data[len - 1];
+
+    // [1, lt) < p, [lt, k) は p 以上 q 以下, (gt, len - 1) > q
+    int lt = 1;
+    int gt = len - 2;
+    int k = 1;
+    while (k <= gt) {
+        if (data[k] < p) {
+            SWAP(data, k, lt);
+            lt += 1;
+        } else if (data[k] > q) {
+            while (data[gt] > q && k < gt) {
+                gt -= 1;
+            }
+            SWAP(data, k, gt);
+            gt -= 1;
+            if (data[k] < p) {
+                SWAP(data, k, lt);
+                lt += 1;
+            }
+        }
+        k += 1;
+    }
+    lt -= 1;
+    gt += 1;
+
+    // pivot を最終的な位置に置く
+    SWAP(data, 0, lt);
+    SWAP(data, len - 1, gt);
+
+    dual_pivot_quicksort_internal(data, lt, recur_limit - 1);
+    // p == q なら中央の要素は全て等しい
+    if (p < q) {
+        dual_pivot_quicksort_internal(data + lt + 1, gt - lt - 1,
+                                      recur_limit - 1);
+    }
+    dual_pivot_quicksort_internal(data + gt + 1, len - gt - 1,
+                                  recur_limit - 1);
+}
+
+void dual_pivot_quicksort(int *data, int len) {
+    if (len <= 1) {
+        return;
+    }
+    dual_pivot_quicksort_internal(data, len, log2(len) * 2);
+}
+
+// Quicksort の pivot の選び方
+// 値は Rust 側の PivotStrategy と一致させること
+enum pivot_strategy {
+    PIVOT_MIDDLE = 0,
+    PIVOT_MEDIAN_OF_THREE = 1,
+    PIVOT_NINTHER = 2,
+    PIVOT_RANDOM = 3,
+};
+
+// これより小さい配列では ninther の代わりに median-of-three を使う
+#define NINTHER_THRESHOLD 128
+
+// 3 つの index のうち、値が中央値になるものを返す
+static inline int median_of_three(int *data, int a, int b, int c) {
+    if (data[a] < data[b]) {
+        if (data[b] < data[c]) {
+            return b;
+        }
+        return data[a] < data[c] ? c : a;
+    }
+    if (data[a] < data[c]) {
+        return a;
+    }
+    return data[b] < data[c] ? c : b;
+}
+
+// https://en.wikipedia.org/wiki/Xorshift
+// rand() は MSVC だと 15bit しかないので自前で用意する
+// 状態は呼び出しごとに持ち、複数のスレッドから同時に呼んでも競合しないようにする
+#define XORSHIFT_SEED 2463534242u
+static inline unsigned int xorshift32(unsigned int *state) {
+    unsigned int x = *state;
+    x ^= x << 13;
+    x ^= x >> 17;
+    x ^= x << 5;
+    *state = x;
+    return x;
+}
+
+static inline int select_pivot(int *data, int len,
+                               enum pivot_strategy strategy,
+                               unsigned int *rng) {
+    int mid = len / 2;
+    switch (strategy) {
+        case PIVOT_MEDIAN_OF_THREE:
+            return median_of_three(data, 0, mid, len - 1);
+        case PIVOT_NINTHER:
+            if (len < NINTHER_THRESHOLD) {
+                return median_of_three(data, 0, mid, len - 1);
+            } else {
+                // 3 つの median-of-three の median-of-three
+                int step = len / 8;
+                int a = median_of_three(data, 0, step, 2 * step);
+                int b = median_of_three(data, mid - step, mid, mid + step);
+                int c = median_of_three(data, len - 1 - 2 * step,
+                                        len - 1 - step, len - 1);
+                return median_of_three(data, a, b, c);
+            }
+        case PIVOT_RANDOM:
+            return xorshift32(rng) % (unsigned int)len;
+        case PIVOT_MIDDLE:
+        default:
+            return mid;
+    }
+}
+
+// heapsort に切り替えない素の Quicksort
+// introsort との比較用。小さい範囲は introsort と同じく挿入ソートでソートする。
+// 再帰の深さを制限しないので、pivot の選び方と入力によっては O(n^2) になる。
+// 例えば PIVOT_MIDDLE は山型 (前半が昇順、後半が降順) の配列で毎回最大値を選ぶ。
+static void quicksort_internal(int *data, int len,
+                               enum pivot_strategy strategy,
+                               unsigned int *rng) {
+    while (len >= INSERTION_SORT_THRESHOLD) {
+        // introsort と同じく、pivot を中央に置いてから分割する
+        int pivot_index = select_pivot(data, len, strategy, rng);
+        SWAP(data, pivot_index, len / 2);
+        int pivot = data[len / 2];
+
+        int partition = block_partition(data, len, pivot);
+
+        // pivot が最小値 (最大値) だと片側が空になって先に進まなくなる。
+        // introsort なら recur_limit で止まるが、ここでは pivot と等しい要素を
+        // 端に集めて取り除く。pivot 自身が含まれるので必ず 1 つ以上減る。
+        if (partition == 0) {
+            int equal = 0;
+            for (int i = 0; i < len; i++) {
+                if (data[i] == pivot) {
+                    SWAP(data, i, equal);
+                    equal += 1;
+                }
+            }
+            data += equal;
+            len -= equal;
+            continue;
+        }
+        if (partition == len) {
+            int equal = 0;
+            for (int i = len - 1; i >= 0; i--) {
+                if (data[i] == pivot) {
+                    SWAP(data, i, len - 1 - equal);
+                    equal += 1;
+                }
+            }
+            len -= equal;
+            continue;
+        }
+
+        // 小さい方だけ再帰し、大きい方はループで処理する。
+        // pivot の選択が悪くてもスタックの深さは O(logn) に収まる。
+        if (partition < len - partition) {
+            quicksort_internal(data, partition, strategy, rng);
+            data += partition;
+            len -= partition;
+        } else {
+            quicksort_internal(data + partition, len - partition, strategy,
+                               rng);
+            len = partition;
+        }
+    }
+    insertion_sort(data, len);
+}
+
+void quicksort_with_pivot(int *data, int len, enum pivot_strategy strategy) {
+    // xorshift は状態が 0 だと 0 しか返さない。
+    // XORSHIFT_SEED は最上位ビットが立っているので、len と混ぜても 0 にならない。
+    unsigned int rng = XORSHIFT_SEED ^ (unsigned int)len;
+    quicksort_internal(data, len, strategy, &rng);
+}
+
+void quicksort(int *data, int len) {
+    quicksort_with_pivot(data, len, PIVOT_MEDIAN_OF_THREE);
+}
+
+// https://en.wikipedia.org/wiki/Quickselect
+// https://en.wikipedia.org/wiki/Introselect
+// data[k] に k 番目 (0-indexed) に小さい要素を置き、
+// data[0, k) はすべて data[k] 以下、data[k + 1, len) はすべて data[k] 以上にする。
+// 分割後は k を含む側だけを処理すればよいので平均 O(n) で終わる。
+// introsort と同じく、分割が偏り続けたら heapsort に切り替えて O(nlogn) に抑える。
+static void select_nth_internal(int *data, int len, int k, int recur_limit) {
+    while (len >= INSERTION_SORT_THRESHOLD) {
+        if (recur_limit == 0) {
+            heapsort(data, len);
+            return;
+        }
+        recur_limit -= 1;
+
+        int pivot_index = median_of_three(data, 0, len / 2, len - 1);
+        SWAP(data, pivot_index, len / 2);
+        int pivot = data[len / 2];
+
+        int partition = block_partition(data, len, pivot);
+
+        // pivot が最小値 (最大値) のときは quicksort_internal と同じく
+        // pivot と等しい要素を端に集める。k がその中にあれば data[k] == pivot で確定する。
+        if (partition == 0) {
+            int equal = 0;
+            for (int i = 0; i < len; i++) {
+                if (data[i] == pivot) {
+                    SWAP(data, i, equal);
+                    equal += 1;
+                }
+            }
+            if (k < equal) {
+                return;
+            }
+            data += equal;
+            len -= equal;
+            k -= equal;
+            continue;
+        }
+        if (partition == len) {
+            int equal = 0;
+            for (int i = len - 1; i >= 0; i--) {
+                if (data[i] == pivot) {
+                    SWAP(data, i, len - 1 - equal);
+                    equal += 1;
+                }
+            }
+            if (k >= len - equal) {
+                return;
+            }
+            len -= equal;
+            continue;
+        }
+
+        if (k < partition) {
+            len = partition;
+        } else {
+            data += partition;
+            len -= partition;
+            k -= partition;
+        }
+    }
+    insertion_sort(data, len);
+}
+
+void select_nth(int *data, int len, int k) {
+    if (k < 0 || k >= len) {
+        return;
+    }
+    select_nth_internal(data, len, k, log2(len) * 2);
+}
+
+// 小さい方から k 個だけをソートして data[0, k) に置く。残りの順序は不定。
+// select_nth で k 個を前に集めてから、その部分だけを introsort する。
+// 全体をソートするより速く O(n + klogk) で終わる。
+void partial_sort(int *data, int len, int k) {
+    if (k <= 0) {
+        return;
+    }
+    if (k < len) {
+        select_nth(data, len, k - 1);
+    } else {
+        k = len;
+    }
+    introsort(data, k);
 }
 
 // 偶数前提で境界を計算しているため、奇数にするならコードの変更が必要
+// 値は Rust 側の BUCKET_SORT_ELEMENT_SIZE と一致させること
 #define BUCKET_SORT_ELEMENT_SIZE 1024  // int が 32bit なら 512 KiB
 // elements: 4
 // count:   0   1  2  3
@@ -240,25 +1238,748 @@ void bucket_sort(int *data, int len) {
     }
 }
 
-void mysort(int *s, int n) {
-    int *data = s;
-    int len = n;
+// 値の範囲 (max - min + 1) が要素数のこの倍以下なら計数ソートする
+#define COUNTING_SORT_SPAN_RATIO 2
 
+// https://en.wikipedia.org/wiki/Counting_sort
+// [0, BUCKET_SORT_ELEMENT_SIZE) に収まらない配列は、最小値と最大値を調べて [min, max] の範囲だけを数える。
+// 負の値や 5000..6000 のような範囲の配列もソートできる。
+// 範囲が広すぎる場合は何もせずに false を返す。
+static bool try_counting_sort(int *data, int len) {
     if (len <= 1) {
-        return;
+        return true;
     }
 
-    if (len <= 65) {
-        introsort(data, len);
+    // 全ての要素が [0, BUCKET_SORT_ELEMENT_SIZE) に収まっていれば bucket_sort と同じく数える。
+    // 範囲外の要素が見つかるまでは分岐予測が当たるので、最小値と最大値を先に求めるより速い。
+    int stack_count[BUCKET_SORT_ELEMENT_SIZE];
+    memset(stack_count, 0, sizeof(stack_count));
+    int in_range = 0;
+    while (in_range < len && 0 <= data[in_range] &&
+           data[in_range] < BUCKET_SORT_ELEMENT_SIZE) {
+        stack_count[data[in_range]] += 1;
+        in_range++;
+    }
+    if (in_range == len) {
+        int *out = data;
+        for (int i = 0; i < BUCKET_SORT_ELEMENT_SIZE; i++) {
+            for (int j = 0; j < stack_count[i]; j++) {
+                *out++ = i;
+            }
+        }
+        return true;
+    }
+
+    int min = data[0];
+    int max = data[0];
+    for (int i = 1; i < len; i++) {
+        min = MIN(min, data[i]);
+        max = MAX(max, data[i]);
+    }
+
+    // int 同士の差は int に収まらないことがある
+    long long span = (long long)max - min + 1;
+    if (span > BUCKET_SORT_ELEMENT_SIZE &&
+        span > (long long)len * COUNTING_SORT_SPAN_RATIO) {
+        return false;
+    }
+
+    // 範囲が狭ければ bucket_sort と同じくスタックに置く
+    int *count = stack_count;
+    if (span > BUCKET_SORT_ELEMENT_SIZE) {
+        count = malloc(sizeof(int) * span);
+        if (count == NULL) {
+            return false;
+        }
+    }
+    memset(count, 0, sizeof(int) * span);
+
+    for (int i = 0; i < len; i++) {
+        count[(long long)data[i] - min] += 1;
+    }
+
+    int data_index = 0;
+    for (long long i = 0; i < span; i++) {
+        for (int j = 0; j < count[i]; j++) {
+            data[data_index++] = (int)(min + i);
+        }
+    }
+
+    if (count != stack_count) {
+        free(count);
+    }
+    return true;
+}
+
+// 値の範囲が狭ければ計数ソート、そうでなければ introsort でソートする
+void counting_sort(int *data, int len) {
+    if (!try_counting_sort(data, len)) {
+        introsort(data, len);
+    }
+}
+
+// 1 パスで見る桁のビット数
+#define RADIX_SORT_BITS 8
+#define RADIX_SORT_BUCKETS (1 << RADIX_SORT_BITS)
+#define RADIX_SORT_PASSES (32 / RADIX_SORT_BITS)
+// 符号ビットを反転すると、負の値も unsigned として大小関係を保ったまま扱える
+#define RADIX_SORT_DIGIT(value, pass)                                      \
+    ((((unsigned int)(value) ^ 0x80000000u) >> ((pass) * RADIX_SORT_BITS)) & \
+     (RADIX_SORT_BUCKETS - 1))
+
+// https://en.wikipedia.org/wiki/Radix_sort
+// 下位の桁から 8bit ずつ安定な計数ソートを繰り返す (LSD radix sort)。
+// 比較を一切しないので、値の範囲が広くて計数ソートできない大きな配列に向く。
+// 作業用のメモリを確保できなかった場合は introsort でソートする。
+void radix_sort(int *data, int len) {
+    if (len <= 1) {
+        return;
+    }
+
+    int *buffer = malloc(sizeof(int) * len);
+    if (buffer == NULL) {
+        introsort(data, len);
         return;
     }
 
+    // 全てのパスのヒストグラムを 1 回の走査でまとめて作る
+    int count[RADIX_SORT_PASSES][RADIX_SORT_BUCKETS];
+    memset(count, 0, sizeof(count));
     for (int i = 0; i < len; i++) {
-        if (!(0 <= data[i] && data[i] < BUCKET_SORT_ELEMENT_SIZE)) {
-            introsort(data, len);
+        for (int pass = 0; pass < RADIX_SORT_PASSES; pass++) {
+            count[pass][RADIX_SORT_DIGIT(data[i], pass)] += 1;
+        }
+    }
+
+    int *src = data;
+    int *dst = buffer;
+    for (int pass = 0; pass < RADIX_SORT_PASSES; pass++) {
+        // 全要素でこの桁が同じなら並びは変わらないので飛ばす
+        if (count[pass][RADIX_SORT_DIGIT(src[0], pass)] == len) {
+            continue;
+        }
+
+        int offset[RADIX_SORT_BUCKETS];
+        int sum = 0;
+        for (int i = 0; i < RADIX_SORT_BUCKETS; i++) {
+            offset[i] = sum;
+            sum += count[pass][i];
+        }
+
+        for (int i = 0; i < len; i++) {
+            dst[offset[RADIX_SORT_DIGIT(src[i], pass)]++] = src[i];
+        }
+
+        int *tmp = src;
+        src = dst;
+        dst = tmp;
+    }
+
+    if (src != data) {
+        memcpy(data, src, sizeof(int) * len);
+    }
+    free(buffer);
+}
+
+// キーと付随するデータの組
+// レイアウトは Rust 側の KeyValue と一致させること
+struct key_value {
+    int key;
+    unsigned int payload;
+};
+
+// 自然なランがこれより短い場合は挿入ソートで伸ばす
+#define MERGE_SORT_MIN_RUN 32
+// ランの長さは少なくともフィボナッチ数列的に増えるので、int の範囲ならこれで足りる
+#define MERGE_SORT_MAX_RUNS 85
+
+// 安定な挿入ソート。data[0..sorted) はソート済みであること
+static void insertion_sort_pairs(struct key_value *data, int sorted,
+                                 int len) {
+    for (int i = MAX(sorted, 1); i < len; i++) {
+        if (data[i - 1].key > data[i].key) {
+            int slide_from = i;
+            struct key_value sliding_value = data[slide_from];
+            do {
+                data[slide_from] = data[slide_from - 1];
+                slide_from -= 1;
+            } while (slide_from > 0 &&
+                     data[slide_from - 1].key > sliding_value.key);
+            data[slide_from] = sliding_value;
+        }
+    }
+}
+
+// data[0..mid) と data[mid..len) のソート済みの列をマージする。
+// 左の列を buffer に退避してから前から詰めていく。
+// キーが等しい場合は左の列を優先するので安定。
+static void merge_pairs(struct key_value *data, int mid, int len,
+                        struct key_value *buffer) {
+    // すでに並んでいる
+    if (data[mid - 1].key <= data[mid].key) {
+        return;
+    }
+
+    memcpy(buffer, data, sizeof(struct key_value) * mid);
+    int l = 0;
+    int r = mid;
+    int out = 0;
+    while (l < mid && r < len) {
+        if (data[r].key < buffer[l].key) {
+            data[out++] = data[r++];
+        } else {
+            data[out++] = buffer[l++];
+        }
+    }
+    memcpy(data + out, buffer + l, sizeof(struct key_value) * (mid - l));
+}
+
+// data の先頭から続くランの長さを返す。
+// 狭義単調減少のランは反転して昇順にする (等しいキーを含むと安定でなくなる)。
+static int find_run_pairs(struct key_value *data, int len) {
+    if (len <= 1) {
+        return len;
+    }
+    int end = 2;
+    if (data[1].key < data[0].key) {
+        while (end < len && data[end].key < data[end - 1].key) {
+            end += 1;
+        }
+        for (int i = 0, j = end - 1; i < j; i++, j--) {
+            struct key_value tmp = data[i];
+            data[i] = data[j];
+            data[j] = tmp;
+        }
+    } else {
+        while (end < len && data[end].key >= data[end - 1].key) {
+            end += 1;
+        }
+    }
+    return end;
+}
+
+// https://en.wikipedia.org/wiki/Timsort
+// 既にソートされている部分 (ラン) を見つけて、TimSort と同じ条件でマージしていく。
+// キーが等しい要素の順番を保つ (安定)。
+void merge_sort_pairs(struct key_value *data, int len) {
+    if (len <= 1) {
+        return;
+    }
+
+    struct key_value *buffer = malloc(sizeof(struct key_value) * len);
+    if (buffer == NULL) {
+        // 遅いが安定なまま終わらせる
+        insertion_sort_pairs(data, 0, len);
+        return;
+    }
+
+    int run_start[MERGE_SORT_MAX_RUNS];
+    int run_len[MERGE_SORT_MAX_RUNS];
+    int runs = 0;
+
+    int start = 0;
+    while (start < len) {
+        int remaining = len - start;
+        int run = find_run_pairs(data + start, remaining);
+        if (run < MERGE_SORT_MIN_RUN) {
+            int extended = MIN(MERGE_SORT_MIN_RUN, remaining);
+            insertion_sort_pairs(data + start, run, extended);
+            run = extended;
+        }
+        run_start[runs] = start;
+        run_len[runs] = run;
+        runs += 1;
+        start += run;
+
+        // スタック上のランの長さが次の条件を満たすまでマージする
+        // (A, B, C, D の順で D が一番新しい)
+        //   C > D, B > C + D, A > B + C
+        // 最後まで読んだら全てマージする
+        while (runs >= 2) {
+            int n = runs;
+            bool finished = start == len;
+            if (!(finished || run_len[n - 2] <= run_len[n - 1] ||
+                  (n >= 3 &&
+                   run_len[n - 3] <= run_len[n - 2] + run_len[n - 1]) ||
+                  (n >= 4 &&
+                   run_len[n - 4] <= run_len[n - 3] + run_len[n - 2]))) {
+                break;
+            }
+            // 短い方と隣り合うランをマージする
+            int at = n - 2;
+            if (n >= 3 && run_len[n - 3] < run_len[n - 1]) {
+                at = n - 3;
+            }
+            merge_pairs(data + run_start[at], run_len[at],
+                        run_len[at] + run_len[at + 1], buffer);
+            run_len[at] += run_len[at + 1];
+            for (int i = at + 1; i < runs - 1; i++) {
+                run_start[i] = run_start[i + 1];
+                run_len[i] = run_len[i + 1];
+            }
+            runs -= 1;
+        }
+    }
+
+    free(buffer);
+}
+
+// qsort と同じく、要素のサイズと比較関数を受け取ってソートする版。
+// 比較関数は a < b なら負、a == b なら 0、a > b なら正を返す。
+// qsort_s のように context をそのまま比較関数に渡す。
+// 比較関数が全順序になっていなくても、配列の外に触れることはない。
+typedef int (*compare_fn)(const void *a, const void *b, void *context);
+
+#define ELEMENT(base, i, size) ((char *)(base) + (size_t)(i) * (size))
+
+static inline void swap_bytes(char *a, char *b, size_t size) {
+    for (size_t i = 0; i < size; i++) {
+        char tmp = a[i];
+        a[i] = b[i];
+        b[i] = tmp;
+    }
+}
+
+// 要素を一時的に置く場所がないので、スライドの代わりにスワップする
+static void insertion_sort_cmp(void *base, int len, size_t size,
+                               compare_fn cmp, void *context) {
+    for (int i = 1; i < len; i++) {
+        for (int j = i; j > 0; j--) {
+            char *prev = ELEMENT(base, j - 1, size);
+            char *current = ELEMENT(base, j, size);
+            if (cmp(prev, current, context) <= 0) {
+                break;
+            }
+            swap_bytes(prev, current, size);
+        }
+    }
+}
+
+static void down_heap_cmp(void *base, int len, size_t size, compare_fn cmp,
+                          void *context, int node) {
+    while (true) {
+        int child = 2 * node + 1;
+        if (child >= len) {
+            break;
+        }
+        if (child + 1 < len && cmp(ELEMENT(base, child, size),
+                                   ELEMENT(base, child + 1, size),
+                                   context) < 0) {
+            child += 1;
+        }
+        if (cmp(ELEMENT(base, node, size), ELEMENT(base, child, size),
+                context) >= 0) {
+            break;
+        }
+        swap_bytes(ELEMENT(base, node, size), ELEMENT(base, child, size),
+                   size);
+        node = child;
+    }
+}
+
+void heapsort_cmp(void *base, int len, size_t size, compare_fn cmp,
+                  void *context) {
+    for (int i = (len / 2) - 1; i >= 0; i--) {
+        down_heap_cmp(base, len, size, cmp, context, i);
+    }
+    for (int i = len - 1; i >= 1; i--) {
+        swap_bytes(ELEMENT(base, 0, size), ELEMENT(base, i, size), size);
+        down_heap_cmp(base, i, size, cmp, context, 0);
+    }
+}
+
+// 3 つの index のうち、値が中央値になるものを返す
+static inline int median_of_three_cmp(void *base, size_t size,
+                                      compare_fn cmp, void *context, int a,
+                                      int b, int c) {
+    char *x = ELEMENT(base, a, size);
+    char *y = ELEMENT(base, b, size);
+    char *z = ELEMENT(base, c, size);
+    if (cmp(x, y, context) < 0) {
+        if (cmp(y, z, context) < 0) {
+            return b;
+        }
+        return cmp(x, z, context) < 0 ? c : a;
+    }
+    if (cmp(x, z, context) < 0) {
+        return a;
+    }
+    return cmp(y, z, context) < 0 ? c : b;
+}
+
+// pivot を先頭に置いて Hoare partition で分割し、pivot の最終的な位置を返す。
+// pivot を番兵にせず範囲を確認しながら進むので、比較関数がおかしくても配列の外に出ない。
+static int partition_cmp(void *base, int len, size_t size, compare_fn cmp,
+                         void *context) {
+    int pivot_index = median_of_three_cmp(base, size, cmp, context, 0,
+                                          len / 2, len - 1);
+    swap_bytes(ELEMENT(base, 0, size), ELEMENT(base, pivot_index, size),
+               size);
+    char *pivot = ELEMENT(base, 0, size);
+
+    // [1, l) は pivot 以下、(r, len) は pivot 以上
+    int l = 1;
+    int r = len - 1;
+    while (true) {
+        while (l <= r && cmp(ELEMENT(base, l, size), pivot, context) < 0) {
+            l += 1;
+        }
+        while (l <= r && cmp(ELEMENT(base, r, size), pivot, context) > 0) {
+            r -= 1;
+        }
+        if (l >= r) {
+            break;
+        }
+        swap_bytes(ELEMENT(base, l, size), ELEMENT(base, r, size), size);
+        l += 1;
+        r -= 1;
+    }
+
+    swap_bytes(pivot, ELEMENT(base, l - 1, size), size);
+    return l - 1;
+}
+
+static void introsort_cmp_internal(void *base, int len, size_t size,
+                                   compare_fn cmp, void *context,
+                                   int recur_limit) {
+    while (len > 1) {
+        if (len < INSERTION_SORT_THRESHOLD) {
+            insertion_sort_cmp(base, len, size, cmp, context);
             return;
         }
+
+        if (recur_limit == 0) {
+            heapsort_cmp(base, len, size, cmp, context);
+            return;
+        }
+        recur_limit -= 1;
+
+        // pivot は最終的な位置にあるので、両側だけをソートすればよい
+        int pivot = partition_cmp(base, len, size, cmp, context);
+        introsort_cmp_internal(base, pivot, size, cmp, context, recur_limit);
+        base = ELEMENT(base, pivot + 1, size);
+        len -= pivot + 1;
+    }
+}
+
+void introsort_cmp(void *base, int len, size_t size, compare_fn cmp,
+                   void *context) {
+    if (len <= 1) {
+        return;
+    }
+    introsort_cmp_internal(base, len, size, cmp, context, log2(len) * 2);
+}
+
+// これ以上の大きさの配列で計数ソートが使えない場合は radix_sort を使う
+// radix_sort は要素数に関係なく RADIX_SORT_PASSES * RADIX_SORT_BUCKETS (1024) 個の
+// カウンタを初期化して走査するので、要素数がそれより少ないとその分が無駄になる
+#define RADIX_SORT_THRESHOLD 1024
+
+// 隣り合う要素の逆転がこの数を超えたら、ほぼ整列済みではないとみなして諦める
+#define PRESORTED_DESCENT_LIMIT 8
+// ほぼ整列済みの配列で、挿入ソートで要素を動かしてよい距離の合計 (要素数の何分の 1 か)
+#define PRESORTED_MOVE_RATIO 8
+
+// pdqsort の partial insertion sort
+// https://github.com/orlp/pdqsort
+// 隣り合う要素の逆転が max_descents 個を超えるか、
+// 挿入ソートで要素を動かした距離の合計が max_moves を超えたら諦めて false を返す。
+// 諦めた場合も data は並び替えられているだけで、要素は失われない。
+static bool partial_insertion_sort(int *data, int len, int max_descents,
+                                   long long max_moves) {
+    int descents = 0;
+    long long moves = 0;
+    // 動かす前の data[i - 1]。逆転は元の並びで数える。
+    int previous = len > 0 ? data[0] : 0;
+    for (int i = 1; i < len; i++) {
+        int sliding_value = data[i];
+        descents += previous > sliding_value;
+        previous = sliding_value;
+        if (descents > max_descents) {
+            return false;
+        }
+
+        if (data[i - 1] > sliding_value) {
+            int slide_from = i;
+            do {
+                data[slide_from] = data[slide_from - 1];
+                slide_from -= 1;
+            } while (slide_from > 0 && data[slide_from - 1] > sliding_value);
+            data[slide_from] = sliding_value;
+
+            moves += i - slide_from;
+            if (moves > max_moves) {
+                return false;
+            }
+        }
+    }
+    return true;
+}
+
+// 配列がすでに昇順、降順、またはほぼ昇順に並んでいればそれを利用してソートし、true を返す。
+// 先頭から順に見ていき、整列済みでないと分かった時点で諦める。
+// ランダムな配列なら数十要素を見るだけで終わる。
+static bool sort_presorted(int *data, int len) {
+    // 先頭の等しい要素を飛ばし、最初に大小が分かれたところで昇順か降順かを決める
+    int i = 1;
+    while (i < len && data[i - 1] == data[i]) {
+        i++;
+    }
+    if (i == len) {
+        return true;
+    }
+
+    if (data[i - 1] > data[i]) {
+        while (i < len && data[i - 1] >= data[i]) {
+            i++;
+        }
+        if (i < len) {
+            return false;
+        }
+        for (int l = 0, r = len - 1; l < r; l++, r--) {
+            SWAP(data, l, r);
+        }
+        return true;
+    }
+
+    return partial_insertion_sort(data, len, PRESORTED_DESCENT_LIMIT,
+                                  len / PRESORTED_MOVE_RATIO);
+}
+
+// pdqsort で、要素がほぼ整列済みだと判断した範囲で動かしてよい要素の数
+#define PDQSORT_PARTIAL_INSERTION_SORT_LIMIT 8
+
+// data[a] <= data[b] にする
+static inline void sort2(int *data, int a, int b) {
+    if (data[b] < data[a]) {
+        SWAP(data, a, b);
+    }
+}
+
+// data[a] <= data[b] <= data[c] にする
+static inline void sort3(int *data, int a, int b, int c) {
+    sort2(data, a, b);
+    sort2(data, b, c);
+    sort2(data, a, b);
+}
+
+// data[0] を pivot として分割し、pivot の最終的な位置を返す。
+// pivot と等しい要素は右側に集まる。
+// 1 回も入れ替えが起きなかった (すでに分割済みだった) 場合は
+// *already_partitioned を true にする。
+// median-of-three か ninther で pivot 以上の要素が右側に置かれていることを前提とする。
+static int pdq_partition_right(int *data, int len, bool *already_partitioned) {
+    int pivot = data[0];
+    int first = 0;
+    int last = len;
+
+    // pivot 以上の最初の要素を探す
+    while (data[++first] < pivot) {
+    }
+
+    // pivot 未満の最後の要素を探す。
+    // first の直前が pivot なら左側に番兵がないので範囲を確認する。
+    if (first - 1 == 0) {
+        while (first < last && !(data[--last] < pivot)) {
+        }
+    } else {
+        while (!(data[--last] < pivot)) {
+        }
+    }
+
+    *already_partitioned = first >= last;
+
+    while (first < last) {
+        SWAP(data, first, last);
+        while (data[++first] < pivot) {
+        }
+        while (!(data[--last] < pivot)) {
+        }
+    }
+
+    int pivot_position = first - 1;
+    data[0] = data[pivot_position];
+    data[pivot_position] = pivot;
+    return pivot_position;
+}
+
+// data[0] を pivot として分割し、pivot の最終的な位置を返す。
+// partition_right と逆に pivot と等しい要素は左側に集まる。
+// 左隣の要素が pivot と等しい (= この範囲に pivot より小さい要素はない) 場合に使い、
+// pivot と等しい要素をまとめて取り除く。
+static int pdq_partition_left(int *data, int len) {
+    int pivot = data[0];
+    int first = 0;
+    int last = len;
+
+    while (pivot < data[--last]) {
+    }
+
+    if (last + 1 == len) {
+        while (first < last && !(pivot < data[++first])) {
+        }
+    } else {
+        while (!(pivot < data[++first])) {
+        }
+    }
+
+    while (first < last) {
+        SWAP(data, first, last);
+        while (pivot < data[--last]) {
+        }
+        while (!(pivot < data[++first])) {
+        }
+    }
+
+    data[0] = data[last];
+    data[last] = pivot;
+    return last;
+}
+
+// 分割が偏ったときに、パターンを崩すために要素を入れ替える
+static inline void pdq_break_patterns(int *data, int len) {
+    if (len < INSERTION_SORT_THRESHOLD) {
+        return;
+    }
+    int quarter = len / 4;
+    SWAP(data, 0, quarter);
+    SWAP(data, len - 1, len - quarter);
+    if (len > NINTHER_THRESHOLD) {
+        SWAP(data, 1, quarter + 1);
+        SWAP(data, 2, quarter + 2);
+        SWAP(data, len - 2, len - (quarter + 1));
+        SWAP(data, len - 3, len - (quarter + 2));
+    }
+}
+
+// leftmost でない場合、data[-1] には前回の pivot があり、
+// data のどの要素もそれ以上であることが保証されている。
+static void pdqsort_internal(int *data, int len, int bad_allowed,
+                             bool leftmost) {
+    while (true) {
+        if (len < INSERTION_SORT_THRESHOLD) {
+            insertion_sort(data, len);
+            return;
+        }
+
+        // pivot を選んで data[0] に置く
+        int mid = len / 2;
+        if (len > NINTHER_THRESHOLD) {
+            sort3(data, 0, mid, len - 1);
+            sort3(data, 1, mid - 1, len - 2);
+            sort3(data, 2, mid + 1, len - 3);
+            sort3(data, mid - 1, mid, mid + 1);
+            SWAP(data, 0, mid);
+        } else {
+            sort3(data, mid, 0, len - 1);
+        }
+
+        // 前回の pivot と等しいなら、この範囲に pivot より小さい要素はない。
+        // pivot と等しい要素を左に集めて、残りだけをソートする。
+        if (!leftmost && !(data[-1] < data[0])) {
+            int pivot_position = pdq_partition_left(data, len);
+            data += pivot_position + 1;
+            len -= pivot_position + 1;
+            continue;
+        }
+
+        bool already_partitioned;
+        int pivot_position =
+            pdq_partition_right(data, len, &already_partitioned);
+        int l_len = pivot_position;
+        int r_len = len - pivot_position - 1;
+
+        if (l_len < len / 8 || r_len < len / 8) {
+            // 分割が大きく偏った。何度も続くなら heapsort に切り替える。
+            bad_allowed -= 1;
+            if (bad_allowed == 0) {
+                heapsort(data, len);
+                return;
+            }
+            pdq_break_patterns(data, l_len);
+            pdq_break_patterns(data + pivot_position + 1, r_len);
+        } else if (already_partitioned &&
+                   partial_insertion_sort(
+                       data, l_len, l_len,
+                       PDQSORT_PARTIAL_INSERTION_SORT_LIMIT) &&
+                   partial_insertion_sort(
+                       data + pivot_position + 1, r_len, r_len,
+                       PDQSORT_PARTIAL_INSERTION_SORT_LIMIT)) {
+            // 分割済みだった範囲は整列済みであることが多い。
+            // 挿入ソートで少し動かすだけで済めばそこで終わる。
+            return;
+        }
+
+        pdqsort_internal(data, l_len, bad_allowed, leftmost);
+        data += pivot_position + 1;
+        len = r_len;
+        leftmost = false;
+    }
+}
+
+// Pattern-defeating Quicksort
+// https://github.com/orlp/pdqsort
+// https://arxiv.org/abs/2106.05123
+// introsort に加えて、偏った分割の後に要素を入れ替えてパターンを崩し、
+// 分割済みの範囲や pivot と等しい要素の塊を見つけて処理を省く。
+void pdqsort(int *data, int len) {
+    if (len <= 1) {
+        return;
+    }
+    pdqsort_internal(data, len, log2(len), true);
+}
+
+// data がすでに昇順なら何もせず、降順なら反転して true を返す。
+// 昇順と降順の箇所が両方見つかった時点で諦めるので、ランダムな配列ならすぐに終わる。
+static bool sort_monotonic(int *data, int len) {
+    bool ascending = true;
+    bool descending = true;
+    for (int i = 1; i < len; i++) {
+        ascending &= data[i - 1] <= data[i];
+        descending &= data[i - 1] >= data[i];
+        if (!ascending && !descending) {
+            return false;
+        }
+    }
+    if (!ascending) {
+        for (int i = 0, j = len - 1; i < j; i++, j--) {
+            SWAP(data, i, j);
+        }
+    }
+    return true;
+}
+
+// これ以下の大きさの配列は small_sort でソートする
+// 値は Rust 側の SMALL_SORT_THRESHOLD と一致させること
+#define SMALL_SORT_THRESHOLD 65
+
+void mysort(int *s, int n) {
+    int *data = s;
+    int len = n;
+
+    if (len <= 1) {
+        return;
+    }
+
+    if (len <= SMALL_SORT_THRESHOLD) {
+        if (!sort_monotonic(data, len)) {
+            small_sort(data, len);
+        }
+        return;
+    }
+
+    if (sort_presorted(data, len)) {
+        return;
+    }
+
+    if (try_counting_sort(data, len)) {
+        return;
+    }
+
+    if (len >= RADIX_SORT_THRESHOLD) {
+        radix_sort(data, len);
+        return;
     }
 
-    bucket_sort(data, len);
+    introsort(data, len);
 }
@@ -0,0 +1,138 @@
+//! ベンチマークの設定。既定値はリポジトリの `bench.yaml` で、
+//! 環境変数 `BENCH_CONFIG` で別の YAML ファイルを指定できる。
+
+use {
+    crate::distribution::Distribution,
+    rand::{rngs::StdRng, SeedableRng},
+    serde::Deserialize,
+    std::{env, error::Error, ffi::c_int, fs, path::PathBuf, time::Duration},
+};
+
+/// 設定ファイルのパスを指定する環境変数
+const CONFIG_ENV: &str = "BENCH_CONFIG";
+
+const DEFAULT_CONFIG: &str = include_str!("../../../bench.yaml");
+
+/// criterion の既定の標本の数
+const DEFAULT_SAMPLE_SIZE: usize = 100;
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// 配列の大きさ
+    pub sizes: Vec<usize>,
+    /// `rand() % modulus` の `modulus`
+    pub modulus: c_int,
+    /// 入力を作る乱数の seed。seed ごとに全てのベンチマークを測る。
+    /// 空なら実行ごとに異なる入力で 1 回ずつ測る。
+    #[serde(default)]
+    pub seeds: Vec<u64>,
+    /// 1 つのベンチマークあたりのウォームアップの時間 (秒)
+    pub warm_up_time: f64,
+    /// 1 つのベンチマークあたりの計測時間 (秒)
+    pub measurement_time: f64,
+    /// 1 つのベンチマークあたりの標本の数。省略すると criterion の既定値。10 以上。
+    /// コマンドライン引数の `--sample-size` より優先される。
+    #[serde(default)]
+    pub sample_size: Option<usize>,
+    pub algorithms: Vec<String>,
+    pub distributions: Vec<Distribution>,
+    pub parallel: Option<ParallelConfig>,
+    pub scaling: Option<ScalingConfig>,
+    /// 計測結果を書き出すファイルのパス。拡張子を `.json` と `.csv` にした 2 つのファイルに書き出す。
+    /// 省略すると書き出さない。
+    #[serde(default)]
+    pub results: Option<PathBuf>,
+}
+
+/// 大きい配列での並列ソートの設定
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ParallelConfig {
+    pub sizes: Vec<usize>,
+    pub algorithms: Vec<String>,
+}
+
+/// スレッド数によるスケーリングの設定
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScalingConfig {
+    pub size: usize,
+    pub threads: Vec<usize>,
+    pub algorithms: Vec<String>,
+}
+
+impl Default for Config {
+    /// リポジトリの `bench.yaml`
+    fn default() -> Self {
+        Self::parse(DEFAULT_CONFIG).expect("bench.yaml should be valid")
+    }
+}
+
+impl Config {
+    /// `BENCH_CONFIG` が指定されていればそのファイルを、なければ `bench.yaml` を読み込む。
+    pub fn load() -> Result<Self, Box<dyn Error>> {
+        match env::var_os(CONFIG_ENV) {
+            Some(path) => {
+                let yaml = fs::read_to_string(&path)
+                    .map_err(|error| format!("{}: {error}", path.to_string_lossy()))?;
+                Self::parse(&yaml)
+            }
+            None => Ok(Self::default()),
+        }
+    }
+
+    pub fn parse(yaml: &str) -> Result<Self, Box<dyn Error>> {
+        let config = serde_yaml::from_str::<Self>(yaml)?;
+
+        if config.modulus <= 0 {
+            return Err(format!("modulus must be positive: {}", config.modulus).into());
+        }
+        for time in [config.warm_up_time, config.measurement_time] {
+            if !(time.is_finite() && time > 0.0) {
+                return Err(format!("time must be positive: {time}").into());
+            }
+        }
+        if config.sample_size() < 10 {
+            return Err(
+                format!("sample_size must be at least 10: {}", config.sample_size()).into(),
+            );
+        }
+        for distribution in &config.distributions {
+            distribution.validate()?;
+        }
+        if let Some(scaling) = &config.scaling {
+            if scaling.threads.contains(&0) {
+                return Err("threads must be positive".into());
+            }
+        }
+
+        Ok(config)
+    }
+
+    pub fn warm_up_time(&self) -> Duration {
+        Duration::from_secs_f64(self.warm_up_time)
+    }
+
+    pub fn measurement_time(&self) -> Duration {
+        Duration::from_secs_f64(self.measurement_time)
+    }
+
+    pub fn sample_size(&self) -> usize {
+        self.sample_size.unwrap_or(DEFAULT_SAMPLE_SIZE)
+    }
+
+    /// 測る seed の一覧。`seeds` が空なら OS の乱数を使う `None` だけを返す。
+    pub fn seeds(&self) -> Vec<Option<u64>> {
+        if self.seeds.is_empty() {
+            vec![None]
+        } else {
+            self.seeds.iter().copied().map(Some).collect()
+        }
+    }
+}
+
+/// `seed` が指定されていればそこから、なければ OS の乱数から乱数生成器を作る。
+pub fn rng(seed: Option<u64>) -> StdRng {
+    seed.map_or_else(StdRng::from_entropy, StdRng::seed_from_u64)
+}
@@ -0,0 +1,181 @@
+//! ベンチマークに使う入力の分布
+
+use {
+    crate::msvc::{MsvcRand, RAND_MAX},
+    rand::{seq::SliceRandom, Rng},
+    rand_distr::{Normal, Zipf},
+    serde::Deserialize,
+    std::{ffi::c_int, fmt, ops::RangeInclusive},
+};
+
+// ほぼ整列済みの配列で入れ替える箇所の数
+const NEARLY_SORTED_SWAPS: usize = 4;
+
+/// `bench.yaml` では `random` や `!uniform { low: 0, high: 1000000 }` のように書く。
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum Distribution {
+    /// 本番と同じく `rand() % modulus`
+    Random,
+    /// 採点環境と同じく、`srand(seed)` してから MSVC の `rand() % modulus` で作った配列。
+    /// 同じ `seed` なら常に同じ配列になる。
+    Msvc { seed: u32 },
+    /// `low..=high` の一様分布
+    Uniform { low: c_int, high: c_int },
+    /// 全て同じ値
+    AllEqual,
+    /// `Random` を昇順に並べたもの
+    Sorted,
+    /// `Random` を降順に並べたもの
+    Reversed,
+    /// 前半が昇順、後半が降順の山型
+    OrganPipe,
+    /// `0..period` の昇順の繰り返し
+    Sawtooth { period: usize },
+    /// ランダムに選んだ `unique` 種類の値だけからなる
+    FewUnique { unique: usize },
+    /// 正規分布に従う値を丸めたもの
+    Gaussian { mean: f64, std_dev: f64 },
+    /// `1..=elements` のうち、k 番目の値が `1 / k^exponent` に比例する頻度で現れる
+    Zipf { elements: u64, exponent: f64 },
+    /// `Sorted` の数箇所で近くの要素を入れ替えたもの
+    NearlySorted,
+    /// `Sorted` の `swaps` 箇所でランダムな 2 要素を入れ替えたもの
+    SortedSwaps { swaps: usize },
+    /// `Random` の先頭 `percent` % だけを昇順に並べたもの
+    SortedPrefix { percent: usize },
+}
+
+impl Distribution {
+    /// 引数が生成できない値になっていないか確認する。
+    pub fn validate(self) -> Result<(), String> {
+        let valid = match self {
+            Self::Uniform { low, high } => low <= high,
+            Self::Sawtooth { period } => period > 0,
+            Self::FewUnique { unique } => unique > 0,
+            Self::Gaussian { mean, std_dev } => Normal::new(mean, std_dev).is_ok(),
+            Self::Zipf { elements, exponent } => Zipf::new(elements, exponent).is_ok(),
+            Self::SortedPrefix { percent } => percent <= 100,
+            _ => true,
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(format!("invalid distribution: {self:?}"))
+        }
+    }
+
+    /// 生成する値が必ず収まる範囲。値の範囲を決められない分布では `None`。
+    pub fn bounds(self, len: usize, modulus: c_int) -> Option<RangeInclusive<c_int>> {
+        let max = |value: usize| value.min(c_int::MAX as usize) as c_int;
+        match self {
+            Self::Random
+            | Self::Msvc { .. }
+            | Self::Sorted
+            | Self::Reversed
+            | Self::NearlySorted
+            | Self::SortedSwaps { .. }
+            | Self::SortedPrefix { .. } => Some(0..=modulus - 1),
+            Self::Uniform { low, high } => Some(low..=high),
+            Self::OrganPipe => Some(0..=max(len.saturating_sub(1) / 2)),
+            Self::Sawtooth { period } => Some(0..=max(period - 1)),
+            Self::Zipf { elements, .. } => Some(1..=elements.min(c_int::MAX as u64) as c_int),
+            Self::AllEqual | Self::FewUnique { .. } | Self::Gaussian { .. } => None,
+        }
+    }
+
+    /// `rand() % modulus` を元にする分布では `modulus` を使う。
+    pub fn generate(self, rng: &mut impl Rng, len: usize, modulus: c_int) -> Vec<c_int> {
+        match self {
+            Self::Random => random(rng, len, modulus),
+            Self::Msvc { seed } => {
+                let mut msvc = MsvcRand::new(seed);
+                (0..len).map(|_| msvc.rand() % modulus).collect()
+            }
+            Self::Uniform { low, high } => (0..len).map(|_| rng.gen_range(low..=high)).collect(),
+            Self::AllEqual => vec![rng.gen(); len],
+            Self::Sorted => {
+                let mut data = random(rng, len, modulus);
+                data.sort_unstable();
+                data
+            }
+            Self::Reversed => {
+                let mut data = random(rng, len, modulus);
+                data.sort_unstable_by(|a, b| b.cmp(a));
+                data
+            }
+            Self::OrganPipe => (0..len).map(|i| i.min(len - 1 - i) as c_int).collect(),
+            Self::Sawtooth { period } => (0..len).map(|i| (i % period) as c_int).collect(),
+            Self::FewUnique { unique } => {
+                let values = (0..unique).map(|_| rng.gen()).collect::<Vec<c_int>>();
+                (0..len).map(|_| *values.choose(rng).unwrap()).collect()
+            }
+            Self::Gaussian { mean, std_dev } => {
+                let normal = Normal::new(mean, std_dev).unwrap();
+                // 範囲外の値は c_int の最小値か最大値になる
+                (0..len)
+                    .map(|_| rng.sample(normal).round() as c_int)
+                    .collect()
+            }
+            Self::Zipf { elements, exponent } => {
+                let zipf = Zipf::new(elements, exponent).unwrap();
+                (0..len).map(|_| rng.sample(zipf) as c_int).collect()
+            }
+            Self::NearlySorted => {
+                let mut data = Self::Sorted.generate(rng, len, modulus);
+                if len >= 2 {
+                    for _ in 0..NEARLY_SORTED_SWAPS {
+                        let i = rng.gen_range(0..len - 1);
+                        let j = (i + rng.gen_range(1..=16)).min(len - 1);
+                        data.swap(i, j);
+                    }
+                }
+                data
+            }
+            Self::SortedSwaps { swaps } => {
+                let mut data = Self::Sorted.generate(rng, len, modulus);
+                if len >= 2 {
+                    for _ in 0..swaps {
+                        data.swap(rng.gen_range(0..len), rng.gen_range(0..len));
+                    }
+                }
+                data
+            }
+            Self::SortedPrefix { percent } => {
+                let mut data = random(rng, len, modulus);
+                data[..len * percent / 100].sort_unstable();
+                data
+            }
+        }
+    }
+}
+
+impl fmt::Display for Distribution {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Self::Random => write!(f, "random"),
+            Self::Msvc { seed } => write!(f, "msvc rand seed = {seed}"),
+            Self::Uniform { low, high } => write!(f, "uniform {low}..={high}"),
+            Self::AllEqual => write!(f, "all equal"),
+            Self::Sorted => write!(f, "sorted"),
+            Self::Reversed => write!(f, "reversed"),
+            Self::OrganPipe => write!(f, "organ pipe"),
+            Self::Sawtooth { period } => write!(f, "sawtooth period = {period}"),
+            Self::FewUnique { unique } => write!(f, "few unique = {unique}"),
+            Self::Gaussian { mean, std_dev } => write!(f, "gaussian mean = {mean} sd = {std_dev}"),
+            Self::Zipf { elements, exponent } => {
+                write!(f, "zipf elements = {elements} s = {exponent}")
+            }
+            Self::NearlySorted => write!(f, "nearly sorted"),
+            Self::SortedSwaps { swaps } => write!(f, "sorted with {swaps} swaps"),
+            Self::SortedPrefix { percent } => write!(f, "sorted prefix {percent}%"),
+        }
+    }
+}
+
+/// `rand() % modulus`
+fn random(rng: &mut impl Rng, len: usize, modulus: c_int) -> Vec<c_int> {
+    (0..len)
+        .map(|_| rng.gen_range(0..RAND_MAX) % modulus)
+        .collect()
+}
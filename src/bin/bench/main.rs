@@ -0,0 +1,869 @@
+mod config;
+mod distribution;
+mod msvc;
+mod report;
+
+use {
+    crate::{
+        config::Config,
+        distribution::Distribution,
+        report::{Measurement, Report},
+    },
+    criterion::{measurement::WallTime, Bencher, BenchmarkGroup, Criterion},
+    sorting_contest::{generic, Kernel, PartitionScheme, PivotStrategy, BUCKET_SORT_ELEMENT_SIZE},
+    std::{
+        error::Error,
+        ffi::{c_int, c_void},
+        mem, process,
+        time::{Duration, Instant},
+    },
+};
+
+extern "C" {
+    // 比較の基準にする libc の qsort
+    fn qsort(
+        base: *mut c_void,
+        len: usize,
+        size: usize,
+        compare: extern "C" fn(*const c_void, *const c_void) -> c_int,
+    );
+}
+
+extern "C" fn compare_c_int(a: *const c_void, b: *const c_void) -> c_int {
+    let (a, b) = unsafe { (*(a as *const c_int), *(b as *const c_int)) };
+    a.cmp(&b) as c_int
+}
+
+fn libc_qsort(data: &mut [c_int]) {
+    unsafe {
+        qsort(
+            data.as_mut_ptr() as *mut c_void,
+            data.len(),
+            mem::size_of::<c_int>(),
+            compare_c_int,
+        );
+    }
+}
+
+type SortFn = fn(&mut [c_int]);
+
+// スレッド数を指定するソート
+type ThreadedSortFn = fn(&mut [c_int], usize);
+
+// 名前を付けたソート。`simd sort` のように実装を選ぶものはクロージャになる
+type NamedSortFn = (String, Box<dyn Fn(&mut [c_int])>);
+
+// ベンチマークできるアルゴリズム。`bench.yaml` ではこの名前で指定する
+const ALGORITHMS: &[(&str, SortFn)] = &[
+    ("mysort", sorting_contest::mysort),
+    ("introsort", sorting_contest::introsort),
+    ("introsort (three-way)", |data| {
+        sorting_contest::introsort_with_partition(data, PartitionScheme::ThreeWay)
+    }),
+    ("pdqsort", sorting_contest::pdqsort),
+    (
+        "dual pivot quicksort",
+        sorting_contest::dual_pivot_quicksort,
+    ),
+    ("par_sort", sorting_contest::par_sort),
+    ("sample sort", sorting_contest::sample_sort),
+    ("quicksort", sorting_contest::quicksort),
+    ("quicksort (middle)", |data| {
+        sorting_contest::quicksort_with_pivot(data, PivotStrategy::Middle)
+    }),
+    ("quicksort (ninther)", |data| {
+        sorting_contest::quicksort_with_pivot(data, PivotStrategy::Ninther)
+    }),
+    ("quicksort (random)", |data| {
+        sorting_contest::quicksort_with_pivot(data, PivotStrategy::Random)
+    }),
+    ("heapsort", sorting_contest::heapsort),
+    ("insertion sort", sorting_contest::insertion_sort),
+    ("small sort", sorting_contest::small_sort),
+    ("counting sort", sorting_contest::counting_sort),
+    ("radix sort", sorting_contest::radix_sort),
+    // 範囲の確認を計測に含めない。前提を満たす分布だけで測る (accepts を参照)
+    ("bucket sort", |data| unsafe {
+        sorting_contest::bucket_sort_unchecked(data)
+    }),
+    ("generic introsort", generic::introsort),
+    ("generic heapsort", generic::heapsort),
+    ("generic mysort", generic::mysort),
+    ("introsort_by", |data| {
+        sorting_contest::introsort_by(data, c_int::cmp)
+    }),
+    ("heapsort_by", |data| {
+        sorting_contest::heapsort_by(data, c_int::cmp)
+    }),
+    // 比較の基準
+    ("sort_unstable", <[c_int]>::sort_unstable),
+    ("sort", <[c_int]>::sort),
+    ("qsort", libc_qsort),
+];
+
+// O(n^2) のアルゴリズム。これより大きい配列では測らない
+const QUADRATIC_ALGORITHMS: &[&str] = &["insertion sort", "small sort"];
+const QUADRATIC_MAX_SIZE: usize = 10_000;
+
+// スレッド数によるスケーリングを測れる並列ソート
+const SCALING_ALGORITHMS: &[(&str, ThreadedSortFn)] = &[
+    ("par_sort", sorting_contest::par_sort_with_threads),
+    ("sample sort", sorting_contest::sample_sort_with_threads),
+];
+
+/// 名前からアルゴリズムを探す。
+/// `simd sort` は、この CPU で使える実装ごとに実装の名前を付けて全て返す。
+fn resolve_algorithms(names: &[String]) -> Result<Vec<NamedSortFn>, String> {
+    let mut algorithms = Vec::<NamedSortFn>::new();
+    for name in names {
+        if name == "simd sort" {
+            for kernel in Kernel::ALL {
+                if kernel.is_available() {
+                    algorithms.push((
+                        format!("simd sort ({})", kernel.name()),
+                        Box::new(move |data| sorting_contest::simd_sort_with_kernel(data, kernel)),
+                    ));
+                }
+            }
+            continue;
+        }
+
+        let &(name, sort_fn) = ALGORITHMS
+            .iter()
+            .find(|(algorithm, _)| algorithm == name)
+            .ok_or_else(|| format!("unknown algorithm: {name}"))?;
+        algorithms.push((name.to_string(), Box::new(sort_fn)));
+    }
+    Ok(algorithms)
+}
+
+fn resolve_scaling_algorithms(names: &[String]) -> Result<Vec<(&str, ThreadedSortFn)>, String> {
+    names
+        .iter()
+        .map(|name| {
+            SCALING_ALGORITHMS
+                .iter()
+                .copied()
+                .find(|(algorithm, _)| algorithm == name)
+                .ok_or_else(|| format!("unknown algorithm for scaling: {name}"))
+        })
+        .collect()
+}
+
+/// 入力によっては使えないか、遅すぎて測れないアルゴリズムを除く。
+fn accepts(name: &str, distribution: Distribution, len: usize, modulus: c_int) -> bool {
+    match name {
+        // bucket_sort_unchecked の前提を満たす分布だけで測る
+        "bucket sort" => distribution.bounds(len, modulus).map_or(false, |bounds| {
+            0 <= *bounds.start() && *bounds.end() < BUCKET_SORT_ELEMENT_SIZE
+        }),
+        // 中央の要素を pivot にすると山型の配列で O(n^2) になる
+        "quicksort (middle)" if distribution == Distribution::OrganPipe => {
+            len <= QUADRATIC_MAX_SIZE
+        }
+        _ => !QUADRATIC_ALGORITHMS.contains(&name) || len <= QUADRATIC_MAX_SIZE,
+    }
+}
+
+fn bench_caller(
+    group: &mut BenchmarkGroup<WallTime>,
+    config: &Config,
+    report: &mut Report,
+    (name, sort_fn): (&str, impl Fn(&mut [c_int])),
+    (distribution, seed): (Distribution, Option<u64>),
+    data_size: usize,
+) {
+    if !accepts(name, distribution, data_size, config.modulus) {
+        return;
+    }
+
+    // criterion はウォームアップの後、標本ごとに 1 回ずつ iter_custom を呼ぶ
+    let mut calls = Vec::new();
+    group.bench_function(
+        format!("{name} n = {data_size}{}", seed_suffix(seed)),
+        |bencher: &mut Bencher| {
+            bencher.iter_custom(|iterations| {
+                let mut rng = config::rng(seed);
+                let mut bench_data = (0..iterations)
+                    .map(|_| distribution.generate(&mut rng, data_size, config.modulus))
+                    .collect::<Vec<_>>();
+
+                let origins = bench_data.clone();
+
+                let start = Instant::now();
+                for i in 0..iterations {
+                    let data = &mut bench_data[i as usize];
+                    sort_fn(data);
+                }
+                let time = start.elapsed();
+
+                for (bench, mut origin) in bench_data.into_iter().zip(origins) {
+                    origin.sort_unstable();
+                    if bench != origin {
+                        panic!("verifying failed: array is not sorted: {bench:#?}");
+                    }
+                }
+
+                calls.push(Duration::from_secs_f64(
+                    time.as_secs_f64() / iterations as f64,
+                ));
+                time
+            })
+        },
+    );
+
+    record(
+        report,
+        Measurement {
+            group: distribution.to_string(),
+            distribution: distribution.to_string(),
+            algorithm: name.to_string(),
+            threads: None,
+            size: data_size,
+            seed,
+            samples: calls,
+        },
+        config.sample_size(),
+    );
+}
+
+/// seed を指定したベンチマークの名前に付ける
+fn seed_suffix(seed: Option<u64>) -> String {
+    seed.map_or_else(String::new, |seed| format!(" seed = {seed}"))
+}
+
+/// `calls` に iter_custom の呼び出しごとの時間を入れた `measurement` から、
+/// ウォームアップを除いた最後の `sample_size` 回を標本として `report` に加える。
+/// `sample_size` はグループに設定した標本の数と同じでなければならない。
+fn record(report: &mut Report, mut measurement: Measurement, sample_size: usize) {
+    // フィルタで除かれたベンチマークは呼ばれない
+    if measurement.samples.is_empty() {
+        return;
+    }
+    let calls = measurement.samples.len();
+    measurement
+        .samples
+        .drain(..calls.saturating_sub(sample_size));
+    report.measurements.push(measurement);
+}
+
+// 分布ごとに 1 つのグループにまとめ、どの分布でどのアルゴリズムが速いかを比べる
+fn bench(c: &mut Criterion, config: &Config, report: &mut Report, algorithms: &[NamedSortFn]) {
+    for &distribution in &config.distributions {
+        let mut group = c.benchmark_group(distribution.to_string());
+        // record で取り除くウォームアップの数が実際の標本の数と合うように、
+        // コマンドライン引数の --sample-size より優先されるグループの設定にする
+        group.sample_size(config.sample_size());
+
+        for (name, sort_fn) in algorithms {
+            for &data_size in &config.sizes {
+                for seed in config.seeds() {
+                    bench_caller(
+                        &mut group,
+                        config,
+                        report,
+                        (name, sort_fn),
+                        (distribution, seed),
+                        data_size,
+                    );
+                }
+            }
+        }
+
+        group.finish();
+    }
+}
+
+// 1 回のソートに数秒かかるので、標本の数を最小にする
+const LARGE_SAMPLE_SIZE: usize = 10;
+
+fn bench_large_caller(
+    group: &mut BenchmarkGroup<WallTime>,
+    config: &Config,
+    report: &mut Report,
+    (name, threads): (&str, Option<usize>),
+    (data_size, seed): (usize, Option<u64>),
+    sort_fn: impl Fn(&mut [c_int]),
+) {
+    let id = match threads {
+        Some(threads) => format!("{name} threads = {threads} n = {data_size}"),
+        None => format!("{name} random n = {data_size}"),
+    } + &seed_suffix(seed);
+
+    let mut calls = Vec::new();
+    group.bench_function(id, |bencher: &mut Bencher| {
+        // 配列が大きいので、bench_caller と違って 1 つずつ生成して確認する
+        bencher.iter_custom(|iterations| {
+            let mut rng = config::rng(seed);
+            let mut time = Duration::ZERO;
+            for _ in 0..iterations {
+                let mut data = Distribution::Random.generate(&mut rng, data_size, config.modulus);
+
+                let start = Instant::now();
+                sort_fn(&mut data);
+                time += start.elapsed();
+
+                if !data.windows(2).all(|w| w[0] <= w[1]) {
+                    panic!("verifying failed: array is not sorted");
+                }
+            }
+            calls.push(Duration::from_secs_f64(
+                time.as_secs_f64() / iterations as f64,
+            ));
+            time
+        })
+    });
+
+    record(
+        report,
+        Measurement {
+            group: if threads.is_some() {
+                "scaling"
+            } else {
+                "parallel"
+            }
+            .to_string(),
+            distribution: Distribution::Random.to_string(),
+            algorithm: name.to_string(),
+            threads,
+            size: data_size,
+            seed,
+            samples: calls,
+        },
+        LARGE_SAMPLE_SIZE,
+    );
+}
+
+// 並列ソートは大きい配列でないと効果がないので別に測る
+fn bench_parallel(
+    c: &mut Criterion,
+    config: &Config,
+    report: &mut Report,
+    sizes: &[usize],
+    algorithms: &[NamedSortFn],
+) {
+    let mut group = c.benchmark_group("parallel");
+    group.sample_size(LARGE_SAMPLE_SIZE);
+
+    for (name, sort_fn) in algorithms {
+        for &data_size in sizes {
+            for seed in config.seeds() {
+                bench_large_caller(
+                    &mut group,
+                    config,
+                    report,
+                    (name, None),
+                    (data_size, seed),
+                    sort_fn,
+                );
+            }
+        }
+    }
+
+    group.finish();
+}
+
+fn bench_scaling(
+    c: &mut Criterion,
+    config: &Config,
+    report: &mut Report,
+    data_size: usize,
+    threads: &[usize],
+    algorithms: &[(&str, ThreadedSortFn)],
+) {
+    let mut group = c.benchmark_group("scaling");
+    group.sample_size(LARGE_SAMPLE_SIZE);
+
+    for &(name, sort_fn) in algorithms {
+        for &threads in threads {
+            for seed in config.seeds() {
+                bench_large_caller(
+                    &mut group,
+                    config,
+                    report,
+                    (name, Some(threads)),
+                    (data_size, seed),
+                    |data| sort_fn(data, threads),
+                );
+            }
+        }
+    }
+
+    group.finish();
+}
+
+fn main() {
+    if let Err(error) = run() {
+        eprintln!("error: {error}");
+        process::exit(1);
+    }
+}
+
+fn run() -> Result<(), Box<dyn Error>> {
+    let config = Config::load()?;
+
+    // 計測を始める前に、全てのアルゴリズムの名前を確認する
+    let algorithms = resolve_algorithms(&config.algorithms)?;
+    let parallel = match &config.parallel {
+        Some(parallel) => Some((parallel, resolve_algorithms(&parallel.algorithms)?)),
+        None => None,
+    };
+    let scaling = match &config.scaling {
+        Some(scaling) => Some((scaling, resolve_scaling_algorithms(&scaling.algorithms)?)),
+        None => None,
+    };
+
+    // コマンドライン引数で指定した値が設定ファイルより優先される。
+    // ただし標本の数はグループごとに設定するので、設定ファイルの sample_size を使う
+    let mut criterion = Criterion::default()
+        .warm_up_time(config.warm_up_time())
+        .measurement_time(config.measurement_time())
+        .configure_from_args();
+
+    let mut report = Report::default();
+    bench(&mut criterion, &config, &mut report, &algorithms);
+    if let Some((parallel, algorithms)) = parallel {
+        bench_parallel(
+            &mut criterion,
+            &config,
+            &mut report,
+            &parallel.sizes,
+            &algorithms,
+        );
+    }
+    if let Some((scaling, algorithms)) = scaling {
+        bench_scaling(
+            &mut criterion,
+            &config,
+            &mut report,
+            scaling.size,
+            &scaling.threads,
+            &algorithms,
+        );
+    }
+
+    criterion.final_summary();
+    report.print_rankings();
+    if let Some(results) = &config.results {
+        report.write_json(&results.with_extension("json"))?;
+        report.write_csv(&results.with_extension("csv"))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        crate::msvc::MsvcRand,
+        pretty_assertions::assert_eq,
+        rand::{thread_rng, Rng},
+        sorting_contest::{BucketSortError, BUCKET_SORT_ELEMENT_SIZE},
+    };
+
+    #[test]
+    fn bucket_sort_test() {
+        macro_rules! sort {
+            ($array:expr) => {{
+                let mut a = $array;
+                sorting_contest::bucket_sort(&mut a).unwrap();
+                a
+            }};
+        }
+
+        let empty: [c_int; 0] = []; // type inference fails
+        assert_eq!(sort!(empty), empty);
+
+        assert_eq!(sort!([0]), [0]);
+        assert_eq!(sort!([1, 2]), [1, 2]);
+        assert_eq!(sort!([5, 8, 9, 3, 5]), [3, 5, 5, 8, 9]);
+        assert_eq!(
+            sort!([10, 9, 8, 7, 6, 5, 4, 3, 2, 1]),
+            [1, 2, 3, 4, 5, 6, 7, 8, 9, 10]
+        );
+        assert_eq!(
+            sort!([BUCKET_SORT_ELEMENT_SIZE - 1, 0]),
+            [0, BUCKET_SORT_ELEMENT_SIZE - 1]
+        );
+
+        // 範囲外の値はソートせずにエラーにする
+        let mut data = [5, 4, 3, 2, 1, 0, -1, -2, -3, -4, -5];
+        assert_eq!(
+            sorting_contest::bucket_sort(&mut data),
+            Err(BucketSortError {
+                index: 6,
+                value: -1
+            })
+        );
+        assert_eq!(data, [5, 4, 3, 2, 1, 0, -1, -2, -3, -4, -5]);
+        assert_eq!(
+            sorting_contest::bucket_sort(&mut [0, 1, BUCKET_SORT_ELEMENT_SIZE]),
+            Err(BucketSortError {
+                index: 2,
+                value: BUCKET_SORT_ELEMENT_SIZE
+            })
+        );
+
+        let mut rng = thread_rng();
+
+        // fuzz
+        for _ in 0..3000 {
+            let mut data = (0..rng.gen_range(0..3000)).collect::<Vec<c_int>>();
+            for d in &mut data {
+                *d = rng.gen_range(0..BUCKET_SORT_ELEMENT_SIZE);
+            }
+
+            let mut origin = data.clone();
+            origin.sort_unstable();
+
+            sorting_contest::bucket_sort(&mut data).unwrap();
+
+            assert_eq!(data, origin);
+        }
+    }
+
+    #[test]
+    fn insertion_sort_test() {
+        generic_test_sort(sorting_contest::insertion_sort);
+    }
+    #[test]
+    fn heapsort_test() {
+        generic_test_sort(sorting_contest::heapsort);
+    }
+    #[test]
+    fn mysort_test() {
+        generic_test_sort(sorting_contest::mysort);
+    }
+    #[test]
+    fn mysort_presorted_test() {
+        let mut rng = thread_rng();
+
+        let config = config::Config::default();
+        for &distribution in &config.distributions {
+            for len in [0, 1, 2, 66, 100, 1000, 10_000] {
+                let mut data = distribution.generate(&mut rng, len, config.modulus);
+                let mut origin = data.clone();
+                origin.sort_unstable();
+                sorting_contest::mysort(&mut data);
+                assert_eq!(data, origin);
+            }
+        }
+    }
+
+    #[test]
+    fn msvc_rand_test() {
+        fn first(seed: u32) -> [c_int; 5] {
+            let mut msvc = MsvcRand::new(seed);
+            [(); 5].map(|_| msvc.rand())
+        }
+
+        // seed = 0, 1 は MSVC の rand() としてよく知られた出力。それ以外は同じ漸化式で計算した値
+        assert_eq!(first(1), [41, 18467, 6334, 26500, 19169]);
+        assert_eq!(first(0), [38, 7719, 21238, 2437, 8855]);
+        assert_eq!(first(42), [175, 400, 17869, 30056, 16083]);
+        assert_eq!(first(12345), [7584, 19164, 25795, 22125, 5828]);
+
+        // 同じ seed からは同じ配列ができる
+        let mut rng = thread_rng();
+        let distribution = Distribution::Msvc { seed: 1 };
+        assert_eq!(
+            distribution.generate(&mut rng, 5, 1000),
+            [41, 467, 334, 500, 169]
+        );
+        assert_eq!(
+            distribution.generate(&mut rng, 1000, 1000),
+            distribution.generate(&mut rng, 1000, 1000)
+        );
+    }
+
+    #[test]
+    fn bench_config_test() {
+        // リポジトリの bench.yaml が読み込めて、全てのアルゴリズムが見つかる
+        let config = config::Config::default();
+        assert_eq!(config.sizes, [100, 10_000, 100_000]);
+        assert_eq!(config.modulus, 1000);
+        assert_eq!(config.seeds(), [None]);
+        assert!(resolve_algorithms(&config.algorithms).is_ok());
+        assert!(resolve_algorithms(&config.parallel.unwrap().algorithms).is_ok());
+        assert!(resolve_scaling_algorithms(&config.scaling.unwrap().algorithms).is_ok());
+
+        let yaml = "
+sizes: [10, 20]
+modulus: 7
+seeds: [42]
+warm_up_time: 0.5
+measurement_time: 1
+algorithms: [mysort, simd sort]
+distributions:
+  - random
+  - !uniform { low: -5, high: 5 }
+";
+        let config = config::Config::parse(yaml).unwrap();
+        assert_eq!(
+            config.distributions,
+            [
+                Distribution::Random,
+                Distribution::Uniform { low: -5, high: 5 }
+            ]
+        );
+        assert!(config.parallel.is_none() && config.scaling.is_none());
+
+        // seed を指定すると毎回同じ入力になる
+        assert_eq!(config.seeds(), [Some(42)]);
+        let data = Distribution::Random.generate(&mut config::rng(Some(42)), 100, config.modulus);
+        assert_eq!(
+            data,
+            Distribution::Random.generate(&mut config::rng(Some(42)), 100, config.modulus)
+        );
+        assert!(data.iter().all(|&x| (0..7).contains(&x)));
+
+        // seed ごとに異なる入力で測る
+        let seeds = config::Config::parse(&yaml.replace("seeds: [42]", "seeds: [1, 2]")).unwrap();
+        assert_eq!(seeds.seeds(), [Some(1), Some(2)]);
+        assert_ne!(
+            Distribution::Random.generate(&mut config::rng(Some(1)), 100, config.modulus),
+            Distribution::Random.generate(&mut config::rng(Some(2)), 100, config.modulus)
+        );
+
+        // simd sort は使える実装の数だけ展開される
+        let algorithms = resolve_algorithms(&config.algorithms).unwrap();
+        assert_eq!(
+            algorithms.len(),
+            1 + Kernel::ALL
+                .iter()
+                .filter(|kernel| kernel.is_available())
+                .count()
+        );
+
+        // 不正な設定は読み込む時点でエラーになる
+        for invalid in [
+            yaml.replace("modulus: 7", "modulus: 0"),
+            yaml.replace("seeds: [42]", "seed: 42"),
+            yaml.replace("measurement_time: 1", "measurement_time: -1"),
+            yaml.replace("high: 5", "high: -6"),
+            yaml.replace("- random", "- !sawtooth { period: 0 }"),
+            yaml.replace("- random", "- bogus"),
+        ] {
+            assert!(config::Config::parse(&invalid).is_err(), "{invalid}");
+        }
+        assert!(resolve_algorithms(&["bogosort".to_string()]).is_err());
+
+        // bench.py の設定は random の分布での mysort だけを測る
+        let threshold = config::Config::parse(include_str!("../../../threshold.yaml")).unwrap();
+        assert_eq!(threshold.algorithms, ["mysort"]);
+        assert_eq!(threshold.distributions, [Distribution::Random]);
+        assert!(threshold.parallel.is_none() && threshold.scaling.is_none());
+    }
+
+    #[test]
+    fn matrix_config_test() {
+        // matrix.yaml は全てのアルゴリズムを測る
+        let config = config::Config::parse(include_str!("../../../matrix.yaml")).unwrap();
+        for &(name, _) in ALGORITHMS {
+            assert!(
+                config.algorithms.iter().any(|algorithm| algorithm == name),
+                "{name}"
+            );
+        }
+        assert!(config
+            .algorithms
+            .iter()
+            .any(|algorithm| algorithm == "simd sort"));
+        assert_eq!(config.sample_size(), 10);
+
+        // 全てのアルゴリズムが正しくソートできる
+        let mut rng = config::rng(config.seeds()[0]);
+        for (name, sort_fn) in resolve_algorithms(&config.algorithms).unwrap() {
+            for &distribution in &config.distributions {
+                if !accepts(&name, distribution, 1000, config.modulus) {
+                    assert_eq!(name, "bucket sort");
+                    continue;
+                }
+                let mut data = distribution.generate(&mut rng, 1000, config.modulus);
+                if let Some(bounds) = distribution.bounds(1000, config.modulus) {
+                    assert!(
+                        data.iter().all(|value| bounds.contains(value)),
+                        "{distribution}"
+                    );
+                }
+                let mut origin = data.clone();
+                origin.sort_unstable();
+                sort_fn(&mut data);
+                assert_eq!(data, origin, "{name} {distribution}");
+            }
+        }
+        assert!(!accepts(
+            "insertion sort",
+            Distribution::Random,
+            QUADRATIC_MAX_SIZE + 1,
+            config.modulus
+        ));
+        assert!(!accepts(
+            "quicksort (middle)",
+            Distribution::OrganPipe,
+            QUADRATIC_MAX_SIZE + 1,
+            config.modulus
+        ));
+        assert!(accepts(
+            "quicksort (middle)",
+            Distribution::Random,
+            QUADRATIC_MAX_SIZE + 1,
+            config.modulus
+        ));
+
+        // 分布と大きさの組ごとに順位を付ける
+        let measurement = |group: &str, algorithm: &str, size, micros| Measurement {
+            group: group.to_string(),
+            distribution: group.to_string(),
+            algorithm: algorithm.to_string(),
+            threads: None,
+            size,
+            seed: None,
+            samples: vec![Duration::from_micros(micros); 10],
+        };
+        let report = Report {
+            measurements: vec![
+                measurement("random", "a", 100, 2),
+                measurement("random", "b", 100, 1),
+                measurement("sorted", "a", 100, 1),
+                measurement("sorted", "b", 100, 4),
+                measurement("random", "a", 1000, 10),
+                measurement("random", "b", 1000, 20),
+                // 一部の組でしか測っていないアルゴリズムは、速くても全体の順位を付けない
+                measurement("random", "d", 1000, 1),
+                // スレッド数を変えた計測は順位に含めない
+                Measurement {
+                    threads: Some(2),
+                    ..measurement("random", "c", 100, 1)
+                },
+            ],
+        };
+        let rankings = report.rankings();
+        assert_eq!(
+            rankings
+                .iter()
+                .map(|ranking| (
+                    ranking.algorithm.as_str(),
+                    ranking.average_rank,
+                    ranking.is_complete()
+                ))
+                .collect::<Vec<_>>(),
+            [("a", 5.0 / 3.0, true), ("b", 2.0, true), ("d", 1.0, false)]
+        );
+        assert_eq!((rankings[2].measured, rankings[2].cells), (1, 3));
+        // 大きさごとの時間は分布をまたいだ幾何平均
+        let b = rankings[1].times[&100].as_secs_f64();
+        assert!((b - 2e-6).abs() < 1e-12, "{b}");
+        // その大きさの全ての分布で測った場合だけ時間を出す
+        assert_eq!(rankings[2].times.keys().collect::<Vec<_>>(), [&1000]);
+        assert_eq!(report::format_time(Duration::from_nanos(1234)), "1.234 µs");
+        assert_eq!(report::format_time(Duration::from_millis(250)), "250.0 ms");
+    }
+
+    #[test]
+    fn bench_results_test() {
+        let measurement = Measurement {
+            group: "uniform 0..=1000000".to_string(),
+            distribution: "uniform 0..=1000000".to_string(),
+            algorithm: "introsort (three-way)".to_string(),
+            threads: None,
+            size: 100,
+            seed: Some(7),
+            samples: [4, 1, 3, 2, 10, 2, 3, 1, 2, 2]
+                .map(Duration::from_micros)
+                .to_vec(),
+        };
+        assert_eq!(measurement.mean(), Duration::from_micros(3));
+        assert_eq!(measurement.median(), Duration::from_micros(2));
+        let (lower, upper) = measurement.confidence_interval();
+        assert!(lower < measurement.mean() && measurement.mean() < upper);
+        assert!(Duration::from_micros(1) <= lower && upper <= Duration::from_micros(10));
+        // 再標本化の乱数は固定している
+        assert_eq!(measurement.confidence_interval(), (lower, upper));
+
+        let report = Report {
+            measurements: vec![
+                measurement.clone(),
+                Measurement {
+                    group: "scaling".to_string(),
+                    distribution: "random".to_string(),
+                    algorithm: "par_sort".to_string(),
+                    threads: Some(4),
+                    size: 1000,
+                    seed: None,
+                    ..measurement
+                },
+            ],
+        };
+
+        let dir = std::env::temp_dir().join(format!("bench-results-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let results = dir.join("results");
+
+        report.write_json(&results.with_extension("json")).unwrap();
+        let json = std::fs::read_to_string(results.with_extension("json")).unwrap();
+        let json = serde_json::from_str::<serde_json::Value>(&json).unwrap();
+        assert_eq!(json[0]["algorithm"], "introsort (three-way)");
+        assert_eq!(json[0]["threads"], serde_json::Value::Null);
+        assert_eq!(json[0]["size"], 100);
+        assert_eq!(json[0]["seed"], 7);
+        assert_eq!(json[0]["mean"], 3000.0);
+        assert_eq!(json[0]["median"], 2000.0);
+        assert_eq!(json[0]["confidence_interval"][0], lower.as_nanos() as f64);
+        assert_eq!(json[0]["samples"].as_array().unwrap().len(), 10);
+        assert_eq!(json[1]["group"], "scaling");
+        assert_eq!(json[1]["threads"], 4);
+
+        report.write_csv(&results.with_extension("csv")).unwrap();
+        let csv = std::fs::read_to_string(results.with_extension("csv")).unwrap();
+        let lines = csv.lines().collect::<Vec<_>>();
+        assert_eq!(
+            lines[0],
+            "group,distribution,algorithm,threads,size,seed,mean,median,ci_lower,ci_upper,samples"
+        );
+        assert!(lines[1].starts_with(
+            "uniform 0..=1000000,uniform 0..=1000000,introsort (three-way),,100,7,3000,2000,"
+        ));
+        assert!(lines[1].ends_with(",4000 1000 3000 2000 10000 2000 3000 1000 2000 2000"));
+        assert!(lines[2].starts_with("scaling,random,par_sort,4,1000,,"));
+        assert_eq!(lines.len(), 3);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    fn generic_test_sort(sort_fn: fn(&mut [i32])) {
+        macro_rules! sort {
+            ($array:expr) => {{
+                let mut a = $array;
+                sort_fn(&mut a);
+                a
+            }};
+        }
+
+        let empty: [c_int; 0] = []; // type inference fails
+        assert_eq!(sort!(empty), empty);
+
+        assert_eq!(sort!([0]), [0]);
+        assert_eq!(sort!([1, 2]), [1, 2]);
+        assert_eq!(sort!([5, 8, 9, 3, 5]), [3, 5, 5, 8, 9]);
+        assert_eq!(
+            sort!([10, 9, 8, 7, 6, 5, 4, 3, 2, 1]),
+            [1, 2, 3, 4, 5, 6, 7, 8, 9, 10]
+        );
+
+        let mut rng = thread_rng();
+
+        // fuzz
+        for _ in 0..3000 {
+            let mut data = (0..3000).collect::<Vec<c_int>>();
+            rng.fill(data.as_mut_slice());
+
+            let mut origin = data.clone();
+            origin.sort_unstable();
+
+            sort_fn(&mut data);
+
+            assert_eq!(data, origin);
+        }
+    }
+}
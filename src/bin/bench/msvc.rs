@@ -0,0 +1,27 @@
+//! MSVC の `rand()` と `srand()` をビット単位で再現する。
+//! https://learn.microsoft.com/en-us/cpp/c-runtime-library/reference/rand
+//! 状態を線形合同法で更新し、その上位 15bit を返す。
+
+use std::ffi::c_int;
+
+// taken from MSVC
+pub const RAND_MAX: c_int = 32767;
+
+/// MSVC の C ランタイムが持つ `rand()` の状態
+#[derive(Clone, Debug)]
+pub struct MsvcRand {
+    state: u32,
+}
+
+impl MsvcRand {
+    /// `srand(seed)` した直後の状態。`srand` を呼ばずに `rand()` した場合は `seed = 1` と同じ。
+    pub fn new(seed: u32) -> Self {
+        Self { state: seed }
+    }
+
+    /// `rand()`
+    pub fn rand(&mut self) -> c_int {
+        self.state = self.state.wrapping_mul(214013).wrapping_add(2531011);
+        ((self.state >> 16) & RAND_MAX as u32) as c_int
+    }
+}
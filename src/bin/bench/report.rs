@@ -0,0 +1,360 @@
+//! criterion とは別に計測結果を集め、全体の順位表と JSON・CSV の結果ファイルを作る。
+
+use {
+    rand::{rngs::StdRng, Rng, SeedableRng},
+    serde::Serialize,
+    std::{
+        collections::BTreeMap,
+        fs::File,
+        io::{self, BufWriter, Write},
+        path::Path,
+        time::Duration,
+    },
+};
+
+/// 信頼区間の信頼水準。criterion の既定値と同じ
+const CONFIDENCE_LEVEL: f64 = 0.95;
+
+/// 信頼区間を求める bootstrap の再標本化の回数
+const BOOTSTRAP_RESAMPLES: usize = 10_000;
+
+/// 1 つのベンチマークの計測結果
+#[derive(Clone, Debug)]
+pub struct Measurement {
+    /// criterion のグループ名。分布ごとのベンチマークでは分布の名前
+    pub group: String,
+    /// 入力の分布の名前
+    pub distribution: String,
+    pub algorithm: String,
+    /// スレッド数を指定した計測ではそのスレッド数
+    pub threads: Option<usize>,
+    pub size: usize,
+    /// 入力を作った乱数の seed。OS の乱数を使った場合は `None`
+    pub seed: Option<u64>,
+    /// 標本ごとの 1 回あたりの時間
+    pub samples: Vec<Duration>,
+}
+
+impl Measurement {
+    /// 標本の平均
+    pub fn mean(&self) -> Duration {
+        self.samples.iter().sum::<Duration>() / self.samples.len() as u32
+    }
+
+    /// 標本の中央値
+    pub fn median(&self) -> Duration {
+        let mut samples = self.samples.clone();
+        samples.sort_unstable();
+        let middle = samples.len() / 2;
+        if samples.len() % 2 == 0 {
+            (samples[middle - 1] + samples[middle]) / 2
+        } else {
+            samples[middle]
+        }
+    }
+
+    /// 平均の 95% 信頼区間。criterion と同じく、標本を復元抽出した平均の分布から求める。
+    /// 再標本化の乱数は固定しているので、同じ標本なら常に同じ区間になる。
+    pub fn confidence_interval(&self) -> (Duration, Duration) {
+        let samples = self
+            .samples
+            .iter()
+            .map(Duration::as_secs_f64)
+            .collect::<Vec<_>>();
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut means = (0..BOOTSTRAP_RESAMPLES)
+            .map(|_| {
+                (0..samples.len())
+                    .map(|_| samples[rng.gen_range(0..samples.len())])
+                    .sum::<f64>()
+                    / samples.len() as f64
+            })
+            .collect::<Vec<_>>();
+        means.sort_by(f64::total_cmp);
+
+        let tail = (1.0 - CONFIDENCE_LEVEL) / 2.0;
+        let percentile = |p: f64| {
+            let index = (p * (means.len() - 1) as f64).round() as usize;
+            Duration::from_secs_f64(means[index])
+        };
+        (percentile(tail), percentile(1.0 - tail))
+    }
+}
+
+/// 結果ファイルの 1 行。時間は全てナノ秒
+#[derive(Debug, Serialize)]
+struct Record<'a> {
+    group: &'a str,
+    distribution: &'a str,
+    algorithm: &'a str,
+    threads: Option<usize>,
+    size: usize,
+    seed: Option<u64>,
+    mean: f64,
+    median: f64,
+    confidence_interval: [f64; 2],
+    samples: Vec<f64>,
+}
+
+impl<'a> From<&'a Measurement> for Record<'a> {
+    fn from(measurement: &'a Measurement) -> Self {
+        let nanos = |time: Duration| time.as_nanos() as f64;
+        let (lower, upper) = measurement.confidence_interval();
+        Self {
+            group: &measurement.group,
+            distribution: &measurement.distribution,
+            algorithm: &measurement.algorithm,
+            threads: measurement.threads,
+            size: measurement.size,
+            seed: measurement.seed,
+            mean: nanos(measurement.mean()),
+            median: nanos(measurement.median()),
+            confidence_interval: [nanos(lower), nanos(upper)],
+            samples: measurement.samples.iter().copied().map(nanos).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Report {
+    pub measurements: Vec<Measurement>,
+}
+
+/// 順位表の 1 行
+#[derive(Clone, Debug)]
+pub struct Ranking {
+    pub algorithm: String,
+    /// 測った組での順位の平均
+    pub average_rank: f64,
+    /// 測った組の数
+    pub measured: usize,
+    /// 全ての組の数。`measured` がこれより少ないアルゴリズムは全体の順位を付けない
+    pub cells: usize,
+    /// 大きさごとの、全ての分布での平均時間の幾何平均。
+    /// その大きさで測っていない分布がある場合は含めない
+    pub times: BTreeMap<usize, Duration>,
+}
+
+impl Ranking {
+    /// 全ての組で測ったかどうか
+    pub fn is_complete(&self) -> bool {
+        self.measured == self.cells
+    }
+}
+
+impl Report {
+    /// コンテストと同じく、グループ・分布・大きさ・seed の組ごとにアルゴリズムを順位付けし、
+    /// 順位の平均が小さい順に並べる。スレッド数を変えた計測は含めない。
+    /// 苦手な入力を測らなかったアルゴリズムが上位に来ないように、
+    /// 全ての組で測ったアルゴリズムを先に並べ、一部の組でしか測っていないものはその後に並べる。
+    pub fn rankings(&self) -> Vec<Ranking> {
+        let ranked = || {
+            self.measurements
+                .iter()
+                .filter(|measurement| measurement.threads.is_none())
+        };
+
+        let mut cells = BTreeMap::<(&str, &str, usize, Option<u64>), Vec<&Measurement>>::new();
+        for measurement in ranked() {
+            cells
+                .entry((
+                    &measurement.group,
+                    &measurement.distribution,
+                    measurement.size,
+                    measurement.seed,
+                ))
+                .or_default()
+                .push(measurement);
+        }
+
+        let mut ranks = BTreeMap::<&str, Vec<usize>>::new();
+        for cell in cells.values_mut() {
+            cell.sort_by_key(|measurement| measurement.mean());
+            for (rank, measurement) in cell.iter().enumerate() {
+                ranks
+                    .entry(&measurement.algorithm)
+                    .or_default()
+                    .push(rank + 1);
+            }
+        }
+
+        let mut cells_per_size = BTreeMap::<usize, usize>::new();
+        for &(_, _, size, _) in cells.keys() {
+            *cells_per_size.entry(size).or_default() += 1;
+        }
+
+        let mut rankings = ranks
+            .into_iter()
+            .map(|(algorithm, ranks)| {
+                let mut log_times = BTreeMap::<usize, Vec<f64>>::new();
+                for measurement in ranked() {
+                    if measurement.algorithm == algorithm {
+                        log_times
+                            .entry(measurement.size)
+                            .or_default()
+                            .push(measurement.mean().as_secs_f64().ln());
+                    }
+                }
+                Ranking {
+                    algorithm: algorithm.to_string(),
+                    average_rank: ranks.iter().sum::<usize>() as f64 / ranks.len() as f64,
+                    measured: ranks.len(),
+                    cells: cells.len(),
+                    times: log_times
+                        .into_iter()
+                        .filter(|(size, logs)| logs.len() == cells_per_size[size])
+                        .map(|(size, logs)| {
+                            let mean = logs.iter().sum::<f64>() / logs.len() as f64;
+                            (size, Duration::from_secs_f64(mean.exp()))
+                        })
+                        .collect(),
+                }
+            })
+            .collect::<Vec<_>>();
+        rankings.sort_by(|a, b| {
+            b.is_complete()
+                .cmp(&a.is_complete())
+                .then(a.average_rank.total_cmp(&b.average_rank))
+        });
+        rankings
+    }
+
+    /// 順位表を表示する。一部の組でしか測っていないアルゴリズムは順位を `-` にし、
+    /// 測った組の数を示す。その大きさで測っていない分布がある場合、時間は `-` にする。
+    pub fn print_rankings(&self) {
+        let rankings = self.rankings();
+        if rankings.is_empty() {
+            return;
+        }
+
+        let mut sizes = self
+            .measurements
+            .iter()
+            .filter(|measurement| measurement.threads.is_none())
+            .map(|measurement| measurement.size)
+            .collect::<Vec<_>>();
+        sizes.sort_unstable();
+        sizes.dedup();
+
+        let width = rankings
+            .iter()
+            .map(|ranking| ranking.algorithm.len())
+            .max()
+            .unwrap()
+            .max("algorithm".len());
+
+        println!();
+        print!(
+            "{:>4}  {:<width$}  {:>8}  {:>9}",
+            "rank", "algorithm", "avg rank", "measured"
+        );
+        for size in &sizes {
+            print!("  {:>12}", format!("n = {size}"));
+        }
+        println!();
+
+        for (rank, ranking) in rankings.iter().enumerate() {
+            let rank = if ranking.is_complete() {
+                (rank + 1).to_string()
+            } else {
+                "-".to_string()
+            };
+            print!(
+                "{:>4}  {:<width$}  {:>8.2}  {:>9}",
+                rank,
+                ranking.algorithm,
+                ranking.average_rank,
+                format!("{}/{}", ranking.measured, ranking.cells)
+            );
+            for size in &sizes {
+                match ranking.times.get(size) {
+                    Some(&time) => print!("  {:>12}", format_time(time)),
+                    None => print!("  {:>12}", "-"),
+                }
+            }
+            println!();
+        }
+    }
+
+    /// 全ての計測結果を JSON で書き出す。
+    pub fn write_json(&self, path: &Path) -> io::Result<()> {
+        let records = self
+            .measurements
+            .iter()
+            .map(Record::from)
+            .collect::<Vec<_>>();
+        let mut writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(&mut writer, &records)?;
+        writeln!(writer)?;
+        writer.flush()
+    }
+
+    /// 全ての計測結果を CSV で書き出す。
+    /// 標本の時間は 1 つの列に空白区切りで並べる。
+    /// スレッド数を指定しない計測では `threads` が、seed を指定しない計測では `seed` が空。
+    pub fn write_csv(&self, path: &Path) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writeln!(
+            writer,
+            "group,distribution,algorithm,threads,size,seed,mean,median,ci_lower,ci_upper,samples"
+        )?;
+        for measurement in &self.measurements {
+            let record = Record::from(measurement);
+            let samples = record
+                .samples
+                .iter()
+                .map(f64::to_string)
+                .collect::<Vec<_>>()
+                .join(" ");
+            writeln!(
+                writer,
+                "{},{},{},{},{},{},{},{},{},{},{}",
+                csv_field(record.group),
+                csv_field(record.distribution),
+                csv_field(record.algorithm),
+                record.threads.map_or_else(String::new, |t| t.to_string()),
+                record.size,
+                record
+                    .seed
+                    .map_or_else(String::new, |seed| seed.to_string()),
+                record.mean,
+                record.median,
+                record.confidence_interval[0],
+                record.confidence_interval[1],
+                samples,
+            )?;
+        }
+        writer.flush()
+    }
+}
+
+/// カンマや引用符を含む値は引用符で囲む (RFC 4180)
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// criterion と同じく、大きさに合わせた単位で有効数字 4 桁程度にする。
+pub fn format_time(time: Duration) -> String {
+    let nanos = time.as_secs_f64() * 1e9;
+    let (value, unit) = if nanos < 1e3 {
+        (nanos, "ns")
+    } else if nanos < 1e6 {
+        (nanos / 1e3, "µs")
+    } else if nanos < 1e9 {
+        (nanos / 1e6, "ms")
+    } else {
+        (nanos / 1e9, "s")
+    };
+    let precision = if value < 10.0 {
+        3
+    } else if value < 100.0 {
+        2
+    } else {
+        1
+    };
+    format!("{value:.precision$} {unit}")
+}
@@ -0,0 +1,99 @@
+//! メモリに収まらない大きさの 32bit 整数のファイルをソートする。
+//!
+//! ```text
+//! extsort [--memory <SIZE>] [--temp-dir <DIR>] <INPUT> <OUTPUT>
+//! ```
+
+use {
+    sorting_contest::external::{self, Config},
+    std::{env, path::PathBuf, process, time::Instant},
+};
+
+const USAGE: &str = "usage: extsort [--memory <SIZE>] [--temp-dir <DIR>] <INPUT> <OUTPUT>
+
+INPUT と OUTPUT は 32bit 整数をリトルエンディアンで並べたファイル。
+
+options:
+    --memory <SIZE>     ソートに使うメモリの大きさ。K, M, G の接尾辞を使える (既定値: 256M)
+    --temp-dir <DIR>    一時ファイルを置くディレクトリ (既定値: システムの一時ディレクトリ)";
+
+struct Args {
+    config: Config,
+    input: PathBuf,
+    output: PathBuf,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut config = Config::default();
+    let mut paths = Vec::new();
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--memory" => {
+                let size = args.next().ok_or("--memory requires a value")?;
+                config.memory_budget = parse_size(&size)?;
+            }
+            "--temp-dir" => {
+                let dir = args.next().ok_or("--temp-dir requires a value")?;
+                config.temp_dir = dir.into();
+            }
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                process::exit(0);
+            }
+            _ if arg.starts_with('-') => return Err(format!("unknown option: {arg}")),
+            _ => paths.push(PathBuf::from(arg)),
+        }
+    }
+
+    let [input, output]: [PathBuf; 2] = paths
+        .try_into()
+        .map_err(|_| "expected exactly two paths: <INPUT> <OUTPUT>")?;
+    Ok(Args {
+        config,
+        input,
+        output,
+    })
+}
+
+/// `64K` や `1G` のような大きさをバイト数にする。
+fn parse_size(size: &str) -> Result<usize, String> {
+    let (number, unit) = match size.char_indices().last() {
+        Some((i, 'K' | 'k')) => (&size[..i], 1 << 10),
+        Some((i, 'M' | 'm')) => (&size[..i], 1 << 20),
+        Some((i, 'G' | 'g')) => (&size[..i], 1 << 30),
+        _ => (size, 1),
+    };
+    number
+        .parse::<usize>()
+        .ok()
+        .and_then(|number| number.checked_mul(unit))
+        .filter(|&bytes| bytes > 0)
+        .ok_or_else(|| format!("invalid size: {size}"))
+}
+
+fn main() {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(message) => {
+            eprintln!("error: {message}\n\n{USAGE}");
+            process::exit(2);
+        }
+    };
+
+    let start = Instant::now();
+    match external::sort_file(&args.input, &args.output, &args.config) {
+        Ok(summary) => eprintln!(
+            "sorted {} values in {:?} ({} runs, {} merges)",
+            summary.values,
+            start.elapsed(),
+            summary.runs,
+            summary.merges
+        ),
+        Err(error) => {
+            eprintln!("error: {error}");
+            process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,407 @@
+//! メモリに収まらない大きさのファイルをソートする外部マージソート。
+//! ファイルは 32bit 整数をリトルエンディアンで並べたもの。
+//!
+//! 入力を `memory_budget` に収まる大きさずつ読み込んで `mysort` でソートし、
+//! 一時ファイルに書き出した整列済みの run を k-way マージして出力する。
+
+use std::{
+    cmp::Reverse,
+    collections::BinaryHeap,
+    ffi::c_int,
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, BufWriter, Read, Write},
+    mem,
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// マージするときに run ごとに確保する読み込み用のバッファの大きさ
+const MERGE_BUFFER_SIZE: usize = 64 * 1024;
+
+/// 一度にマージする run の最大数。開くファイルの数を抑える。
+const MAX_MERGE_WAYS: usize = 256;
+
+/// `mysort` が run のソートに確保する作業領域の、run の要素数に対する最大の倍率。
+/// 計数ソートの数え上げの配列が最も大きく、値の範囲が要素数の 2 倍まで使う。
+const RUN_SORT_SCRATCH_RATIO: usize = crate::COUNTING_SORT_SPAN_RATIO as usize;
+
+/// 一時ファイルとディレクトリの名前を重複させないための通し番号
+static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// 外部マージソートの設定
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// ソートのために使うメモリの大きさ (バイト)
+    pub memory_budget: usize,
+    /// run を書き出す一時ファイルを置くディレクトリ
+    pub temp_dir: PathBuf,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            memory_budget: 256 * 1024 * 1024,
+            temp_dir: std::env::temp_dir(),
+        }
+    }
+}
+
+/// ソートした結果の統計
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Summary {
+    /// ソートした整数の数
+    pub values: u64,
+    /// 最初に書き出した run の数。1 なら一時ファイルを使わずにメモリ内でソートした。
+    pub runs: usize,
+    /// マージした回数
+    pub merges: usize,
+}
+
+/// `input` のファイルをソートして `output` に書き出す。
+/// 結果は `output` と同じディレクトリの一時ファイルに書き出してから置き換えるので、
+/// `input` と `output` が同じファイルでもよく、失敗したときは `output` を変更しない。
+pub fn sort_file(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    config: &Config,
+) -> io::Result<Summary> {
+    let input = File::open(input)?;
+    let output = TempFile::new(output.as_ref())?;
+    let summary = sort_stream(input, &output.file, config)?;
+    output.persist()?;
+    Ok(summary)
+}
+
+/// `input` から読み込んだ整数をソートして `output` に書き出す。
+pub fn sort_stream(input: impl Read, output: impl Write, config: &Config) -> io::Result<Summary> {
+    let ways = (config.memory_budget / MERGE_BUFFER_SIZE).clamp(2, MAX_MERGE_WAYS);
+    // マージでは run ごとの読み込みと出力にそれぞれバッファを使う
+    let buffer_size = (config.memory_budget / (ways + 1)).max(mem::size_of::<c_int>());
+    // run を作るときは入力と run の書き出しのバッファを除いた残りに、
+    // 読み込んだ整数と mysort が確保する作業領域を置く
+    let chunk_len = (config.memory_budget.saturating_sub(2 * buffer_size)
+        / (mem::size_of::<c_int>() * (1 + RUN_SORT_SCRATCH_RATIO)))
+        .clamp(1, c_int::MAX as usize);
+
+    let mut input = BufReader::with_capacity(buffer_size, input);
+    let mut chunk = Vec::with_capacity(chunk_len);
+    let mut summary = Summary {
+        values: 0,
+        runs: 0,
+        merges: 0,
+    };
+
+    // 全体がメモリに収まるなら一時ファイルを使わない
+    read_chunk(&mut input, &mut chunk, chunk_len)?;
+    if input.fill_buf()?.is_empty() {
+        crate::mysort(&mut chunk);
+        let mut output = BufWriter::with_capacity(buffer_size, output);
+        write_values(&mut output, &chunk)?;
+        output.flush()?;
+        summary.values = chunk.len() as u64;
+        summary.runs = 1;
+        return Ok(summary);
+    }
+
+    let temp_dir = TempDir::new(&config.temp_dir)?;
+    let mut runs = Vec::new();
+    while !chunk.is_empty() {
+        crate::mysort(&mut chunk);
+        let path = temp_dir.run_path(runs.len());
+        let mut run = BufWriter::with_capacity(buffer_size, File::create(&path)?);
+        write_values(&mut run, &chunk)?;
+        run.flush()?;
+        runs.push(path);
+
+        summary.values += chunk.len() as u64;
+        read_chunk(&mut input, &mut chunk, chunk_len)?;
+    }
+    drop(chunk);
+    summary.runs = runs.len();
+
+    // 一度にマージしきれない場合は、先頭の run から順にまとめて新しい run にする
+    let mut next_run = runs.len();
+    while runs.len() > ways {
+        let path = temp_dir.run_path(next_run);
+        next_run += 1;
+        let mut run = BufWriter::with_capacity(buffer_size, File::create(&path)?);
+        merge_runs(&runs[..ways], &mut run, buffer_size)?;
+        run.flush()?;
+        for merged in runs.drain(..ways) {
+            fs::remove_file(merged)?;
+        }
+        runs.push(path);
+        summary.merges += 1;
+    }
+
+    let mut output = BufWriter::with_capacity(buffer_size, output);
+    merge_runs(&runs, &mut output, buffer_size)?;
+    output.flush()?;
+    summary.merges += 1;
+
+    Ok(summary)
+}
+
+/// `chunk` を空にしてから、最大で `chunk_len` 個の整数を読み込む。
+fn read_chunk(
+    input: &mut impl BufRead,
+    chunk: &mut Vec<c_int>,
+    chunk_len: usize,
+) -> io::Result<()> {
+    chunk.clear();
+    while chunk.len() < chunk_len {
+        match read_value(input)? {
+            Some(value) => chunk.push(value),
+            None => break,
+        }
+    }
+    Ok(())
+}
+
+/// 整数を 1 つ読み込む。ファイルの終わりなら `None` を返す。
+fn read_value(input: &mut impl BufRead) -> io::Result<Option<c_int>> {
+    if input.fill_buf()?.is_empty() {
+        return Ok(None);
+    }
+    let mut bytes = [0; mem::size_of::<c_int>()];
+    input.read_exact(&mut bytes).map_err(|error| {
+        if error.kind() == io::ErrorKind::UnexpectedEof {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "file size is not a multiple of 4 bytes",
+            )
+        } else {
+            error
+        }
+    })?;
+    Ok(Some(c_int::from_le_bytes(bytes)))
+}
+
+fn write_values(output: &mut impl Write, values: &[c_int]) -> io::Result<()> {
+    for value in values {
+        output.write_all(&value.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+/// 各 run の先頭の整数を二分ヒープに入れ、最小のものから順に書き出す。
+fn merge_runs(runs: &[PathBuf], output: &mut impl Write, buffer_size: usize) -> io::Result<()> {
+    let mut readers = runs
+        .iter()
+        .map(|path| Ok(BufReader::with_capacity(buffer_size, File::open(path)?)))
+        .collect::<io::Result<Vec<_>>>()?;
+
+    let mut heap = BinaryHeap::with_capacity(readers.len());
+    for (index, reader) in readers.iter_mut().enumerate() {
+        if let Some(value) = read_value(reader)? {
+            heap.push(Reverse((value, index)));
+        }
+    }
+
+    while let Some(Reverse((value, index))) = heap.pop() {
+        output.write_all(&value.to_le_bytes())?;
+        if let Some(next) = read_value(&mut readers[index])? {
+            heap.push(Reverse((next, index)));
+        }
+    }
+    Ok(())
+}
+
+/// run を置く一時ディレクトリ。drop したときに中身ごと削除する。
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(parent: &Path) -> io::Result<Self> {
+        loop {
+            let path = parent.join(format!(
+                "sorting-contest-{}-{}",
+                process::id(),
+                TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+            ));
+            match fs::create_dir(&path) {
+                Ok(()) => return Ok(Self(path)),
+                Err(error) if error.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(error) => return Err(error),
+            }
+        }
+    }
+
+    fn run_path(&self, index: usize) -> PathBuf {
+        self.0.join(format!("run-{index}"))
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// 出力先と同じディレクトリに作る一時ファイル。
+/// `persist` で出力先に置き換え、そうせずに drop したときは削除する。
+struct TempFile {
+    file: File,
+    path: PathBuf,
+    target: PathBuf,
+}
+
+impl TempFile {
+    fn new(target: &Path) -> io::Result<Self> {
+        let name = target.file_name().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "output path has no file name")
+        })?;
+        let parent = match target.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        loop {
+            let path = parent.join(format!(
+                ".{}.sorting-contest-{}-{}",
+                name.to_string_lossy(),
+                process::id(),
+                TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+            ));
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(file) => {
+                    return Ok(Self {
+                        file,
+                        path,
+                        target: target.to_path_buf(),
+                    })
+                }
+                Err(error) if error.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(error) => return Err(error),
+            }
+        }
+    }
+
+    fn persist(self) -> io::Result<()> {
+        fs::rename(&self.path, &self.target)
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        // persist した後は path が存在しないので何もしない
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        pretty_assertions::assert_eq,
+        rand::{thread_rng, Rng},
+    };
+
+    #[test]
+    fn external_sort_test() {
+        let dir = std::env::temp_dir().join(format!("external-sort-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("input");
+        let output = dir.join("output");
+
+        let mut rng = thread_rng();
+        // メモリ内に収まる場合、一度にマージできる場合、何度もマージする場合
+        for (len, memory_budget) in [
+            (0, 1024),
+            (1000, 1 << 20),
+            (100_000, 1 << 18),
+            (100_000, 1 << 12),
+        ] {
+            let mut data = vec![0 as c_int; len];
+            rng.fill(data.as_mut_slice());
+            std::fs::write(
+                &input,
+                data.iter()
+                    .flat_map(|x| x.to_le_bytes())
+                    .collect::<Vec<_>>(),
+            )
+            .unwrap();
+
+            let config = Config {
+                memory_budget,
+                temp_dir: dir.clone(),
+            };
+            let summary = sort_file(&input, &output, &config).unwrap();
+            assert_eq!(summary.values, len as u64);
+
+            let sorted = std::fs::read(&output)
+                .unwrap()
+                .chunks_exact(4)
+                .map(|bytes| c_int::from_le_bytes(bytes.try_into().unwrap()))
+                .collect::<Vec<_>>();
+            data.sort();
+            assert_eq!(sorted, data);
+        }
+
+        // 一時ファイルが残っていない
+        let mut entries = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect::<Vec<_>>();
+        entries.sort();
+        assert_eq!(entries, ["input", "output"]);
+
+        // 4 バイトの倍数でないファイルはエラーになる
+        std::fs::write(&input, [0; 6]).unwrap();
+        let error = sort_file(&input, &output, &Config::default()).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn sort_file_output_test() {
+        let dir =
+            std::env::temp_dir().join(format!("sort-file-output-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("input");
+        let output = dir.join("output");
+
+        // 入力と出力が同じファイルでも、読み込む前に切り詰めない
+        let mut data = vec![0 as c_int; 100_000];
+        thread_rng().fill(data.as_mut_slice());
+        let bytes = data
+            .iter()
+            .flat_map(|x| x.to_le_bytes())
+            .collect::<Vec<_>>();
+        for memory_budget in [1 << 20, 1 << 16] {
+            std::fs::write(&input, &bytes).unwrap();
+            let config = Config {
+                memory_budget,
+                temp_dir: dir.clone(),
+            };
+            let summary = sort_file(&input, &input, &config).unwrap();
+            assert_eq!(summary.values, data.len() as u64);
+
+            let sorted = std::fs::read(&input)
+                .unwrap()
+                .chunks_exact(4)
+                .map(|bytes| c_int::from_le_bytes(bytes.try_into().unwrap()))
+                .collect::<Vec<_>>();
+            let mut expected = data.clone();
+            expected.sort();
+            assert_eq!(sorted, expected);
+        }
+
+        // 入力が不正なら、既存の出力を変更しない
+        std::fs::write(&input, [0; 6]).unwrap();
+        std::fs::write(&output, b"previous").unwrap();
+        let error = sort_file(&input, &output, &Config::default()).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(std::fs::read(&output).unwrap(), b"previous");
+
+        // 一時ファイルが残っていない
+        let mut entries = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect::<Vec<_>>();
+        entries.sort();
+        assert_eq!(entries, ["input", "output"]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
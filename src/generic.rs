@@ -0,0 +1,574 @@
+//! `mysort.c` のアルゴリズムを Rust に移植し、`int` 以外の要素型でもソートできるようにしたもの。
+//! 要素の比較は [`SortElement::less`] で行う。
+//!
+//! [`mysort`] は `mysort.c` の `mysort` と同じ順に手法を選ぶ。
+//! 計数ソートと基数ソートは、大小関係を保ったまま 64bit 以下の整数に変換できる型
+//! ([`SortElement::RADIX_BITS`] が 0 でない型) でだけ使う。
+//!
+//! 次のものは移植していない。
+//! - `sorting_network` と `small_sort`: 比較器の並びが `int` の min/max 命令に合わせたもので、
+//!   一般の比較では挿入ソートより速くならない。代わりに `insertion_sort` を使う。
+//! - `pdqsort` と `dual_pivot_quicksort`: `mysort` の選択には使われず、
+//!   `int` で introsort と比べるための実装なので C の実装だけを残している。
+
+/// ソートできる要素の型
+pub trait SortElement: Copy {
+    /// 基数ソートに使うキーのビット数。8 の倍数で 64 以下。
+    /// 0 ならキーを持たず、計数ソートと基数ソートを使わない。
+    const RADIX_BITS: u32 = 0;
+
+    /// 全順序で `self < other` かどうか
+    fn less(&self, other: &Self) -> bool;
+
+    /// 大小関係を保ったまま `RADIX_BITS` ビットの符号なし整数に変換した値。
+    /// `a.less(&b)` と `a.radix_key() < b.radix_key()` が一致しなければならない。
+    #[inline]
+    fn radix_key(&self) -> u64 {
+        0
+    }
+}
+
+macro_rules! impl_sort_element_for_ord {
+    ($($ty:ty),+$(,)?) => {
+        $(impl SortElement for $ty {
+            #[inline]
+            fn less(&self, other: &Self) -> bool {
+                self < other
+            }
+        })+
+    };
+}
+
+// 64bit に収まらないので基数ソートしない
+impl_sort_element_for_ord!(i128, u128);
+
+macro_rules! impl_sort_element_for_unsigned {
+    ($($ty:ty),+$(,)?) => {
+        $(impl SortElement for $ty {
+            const RADIX_BITS: u32 = <$ty>::BITS;
+
+            #[inline]
+            fn less(&self, other: &Self) -> bool {
+                self < other
+            }
+
+            #[inline]
+            fn radix_key(&self) -> u64 {
+                *self as u64
+            }
+        })+
+    };
+}
+
+impl_sort_element_for_unsigned!(u8, u16, u32, u64, usize);
+
+macro_rules! impl_sort_element_for_signed {
+    ($($ty:ty => $unsigned:ty),+$(,)?) => {
+        // 符号ビットを反転すると、負の値も符号なし整数として大小関係を保ったまま扱える
+        $(impl SortElement for $ty {
+            const RADIX_BITS: u32 = <$ty>::BITS;
+
+            #[inline]
+            fn less(&self, other: &Self) -> bool {
+                self < other
+            }
+
+            #[inline]
+            fn radix_key(&self) -> u64 {
+                (*self as $unsigned ^ <$ty>::MIN as $unsigned) as u64
+            }
+        })+
+    };
+}
+
+impl_sort_element_for_signed!(i8 => u8, i16 => u16, i32 => u32, i64 => u64, isize => usize);
+
+macro_rules! impl_sort_element_for_float {
+    ($($ty:ty => $bits:ty),+$(,)?) => {
+        // `<` は NaN を含むと全順序にならないので `total_cmp` を使う。
+        // -NaN < -inf < ... < -0.0 < +0.0 < ... < +inf < +NaN の順に並ぶ。
+        $(impl SortElement for $ty {
+            const RADIX_BITS: u32 = <$bits>::BITS;
+
+            #[inline]
+            fn less(&self, other: &Self) -> bool {
+                self.total_cmp(other).is_lt()
+            }
+
+            // total_cmp と同じく、負の値は全ビットを、正の値は符号ビットだけを反転する
+            #[inline]
+            fn radix_key(&self) -> u64 {
+                let bits = self.to_bits();
+                let sign = 1 << (<$bits>::BITS - 1);
+                (if bits & sign != 0 { !bits } else { bits | sign }) as u64
+            }
+        })+
+    };
+}
+
+impl_sort_element_for_float!(f32 => u32, f64 => u64);
+
+/// `mysort.c` の `PARTITION_BLOCK`
+const PARTITION_BLOCK: usize = 128;
+
+/// `mysort.c` の `RADIX_SORT_THRESHOLD`
+const RADIX_SORT_THRESHOLD: usize = 1024;
+
+/// `mysort.c` の `RADIX_SORT_BITS`
+const RADIX_SORT_BITS: u32 = 8;
+
+/// `mysort.c` の `RADIX_SORT_BUCKETS`
+const RADIX_SORT_BUCKETS: usize = 1 << RADIX_SORT_BITS;
+
+/// `mysort.c` の `PRESORTED_DESCENT_LIMIT`
+const PRESORTED_DESCENT_LIMIT: usize = 8;
+
+/// `mysort.c` の `PRESORTED_MOVE_RATIO`
+const PRESORTED_MOVE_RATIO: usize = 8;
+
+/// `mysort.c` の `insertion_sort`
+pub fn insertion_sort<T: SortElement>(data: &mut [T]) {
+    for i in 1..data.len() {
+        if data[i].less(&data[i - 1]) {
+            let mut slide_from = i;
+            let sliding_value = data[slide_from];
+            loop {
+                data[slide_from] = data[slide_from - 1];
+                slide_from -= 1;
+                if !(slide_from > 0 && sliding_value.less(&data[slide_from - 1])) {
+                    break;
+                }
+            }
+            data[slide_from] = sliding_value;
+        }
+    }
+}
+
+/// `mysort.c` の `down_heap`
+fn down_heap<T: SortElement>(data: &mut [T], mut node: usize) {
+    loop {
+        let mut child = 2 * node + 1;
+        if child >= data.len() {
+            break;
+        }
+        if child + 1 < data.len() && data[child].less(&data[child + 1]) {
+            child += 1;
+        }
+        if !data[node].less(&data[child]) {
+            break;
+        }
+        data.swap(node, child);
+        node = child;
+    }
+}
+
+/// `mysort.c` の `heapsort`
+pub fn heapsort<T: SortElement>(data: &mut [T]) {
+    let len = data.len();
+    for i in (0..len / 2).rev() {
+        down_heap(data, i);
+    }
+    for i in (1..len).rev() {
+        data.swap(0, i);
+        down_heap(&mut data[..i], 0);
+    }
+}
+
+/// `mysort.c` の `block_partition`
+/// `pivot` は `data` の要素でなければならない。
+fn block_partition<T: SortElement>(data: &mut [T], pivot: T) -> usize {
+    let mut l_offsets = [0; PARTITION_BLOCK];
+    let mut r_offsets = [0; PARTITION_BLOCK];
+    let mut l_start = 0;
+    let mut r_start = 0;
+    let mut l_len = 0;
+    let mut r_len = 0;
+    let mut l = 0;
+    let mut r = data.len() - 1;
+
+    while r - l + 1 > 2 * PARTITION_BLOCK {
+        if l_len == 0 {
+            l_start = 0;
+            for i in 0..PARTITION_BLOCK {
+                l_offsets[l_len] = i;
+                l_len += pivot.less(&data[l + i]) as usize;
+            }
+        }
+        if r_len == 0 {
+            r_start = 0;
+            for i in 0..PARTITION_BLOCK {
+                r_offsets[r_len] = i;
+                r_len += data[r - i].less(&pivot) as usize;
+            }
+        }
+        let num = l_len.min(r_len);
+        for i in 0..num {
+            data.swap(l + l_offsets[l_start + i], r - r_offsets[r_start + i]);
+        }
+        l_len -= num;
+        r_len -= num;
+        l_start += num;
+        r_start += num;
+        if l_len == 0 {
+            l += PARTITION_BLOCK;
+        }
+        if r_len == 0 {
+            r -= PARTITION_BLOCK;
+        }
+    }
+
+    if r_len > 0 && l_len == 0 {
+        loop {
+            if pivot.less(&data[l]) {
+                data.swap(l, r - r_offsets[r_start]);
+                r_len -= 1;
+                r_start += 1;
+                if r_len == 0 {
+                    break;
+                }
+            }
+            if l >= r - r_offsets[r_start] {
+                return l + 1;
+            }
+            l += 1;
+        }
+    } else if l_len > 0 && r_len == 0 {
+        loop {
+            if data[r].less(&pivot) {
+                data.swap(r, l + l_offsets[l_start]);
+                l_len -= 1;
+                l_start += 1;
+                if l_len == 0 {
+                    break;
+                }
+            }
+            if l + l_offsets[l_start] >= r {
+                return r;
+            }
+            r -= 1;
+        }
+    }
+
+    loop {
+        while data[l].less(&pivot) {
+            l += 1;
+        }
+        while pivot.less(&data[r]) {
+            r -= 1;
+        }
+        if l >= r {
+            break;
+        }
+        data.swap(l, r);
+        l += 1;
+        r -= 1;
+    }
+    l
+}
+
+/// `mysort.c` の `introsort`
+pub fn introsort<T: SortElement>(data: &mut [T]) {
+    let recur_limit = 2 * (data.len().max(1)).ilog2();
+    introsort_internal(data, recur_limit);
+
+    fn introsort_internal<T: SortElement>(data: &mut [T], recur_limit: u32) {
+        let len = data.len();
+
+        if len <= 1 {
+            return;
+        }
+
+        if len < crate::INSERTION_SORT_THRESHOLD {
+            insertion_sort(data);
+            return;
+        }
+
+        if recur_limit == 0 {
+            heapsort(data);
+            return;
+        }
+
+        let pivot = data[len / 2];
+        let partition = block_partition(data, pivot);
+
+        let (left, right) = data.split_at_mut(partition);
+        introsort_internal(left, recur_limit - 1);
+        introsort_internal(right, recur_limit - 1);
+    }
+}
+
+/// `mysort.c` の `mysort`。
+/// ソーティングネットワークの代わりに `insertion_sort` を使い、
+/// 計数ソートと基数ソートは `RADIX_BITS` が 0 でない型でだけ使う。
+pub fn mysort<T: SortElement>(data: &mut [T]) {
+    let len = data.len();
+
+    if len <= 1 {
+        return;
+    }
+
+    if len <= crate::SMALL_SORT_THRESHOLD {
+        insertion_sort(data);
+        return;
+    }
+
+    if sort_presorted(data) {
+        return;
+    }
+
+    if T::RADIX_BITS > 0 {
+        if try_counting_sort(data) {
+            return;
+        }
+
+        if len >= RADIX_SORT_THRESHOLD {
+            radix_sort(data);
+            return;
+        }
+    }
+
+    introsort(data);
+}
+
+/// `mysort.c` の `partial_insertion_sort`
+fn partial_insertion_sort<T: SortElement>(
+    data: &mut [T],
+    max_descents: usize,
+    max_moves: usize,
+) -> bool {
+    if data.is_empty() {
+        return true;
+    }
+    let mut descents = 0;
+    let mut moves = 0;
+    // 動かす前の data[i - 1]。逆転は元の並びで数える。
+    let mut previous = data[0];
+    for i in 1..data.len() {
+        let sliding_value = data[i];
+        descents += sliding_value.less(&previous) as usize;
+        previous = sliding_value;
+        if descents > max_descents {
+            return false;
+        }
+
+        if sliding_value.less(&data[i - 1]) {
+            let mut slide_from = i;
+            loop {
+                data[slide_from] = data[slide_from - 1];
+                slide_from -= 1;
+                if !(slide_from > 0 && sliding_value.less(&data[slide_from - 1])) {
+                    break;
+                }
+            }
+            data[slide_from] = sliding_value;
+
+            moves += i - slide_from;
+            if moves > max_moves {
+                return false;
+            }
+        }
+    }
+    true
+}
+
+/// `mysort.c` の `sort_presorted`
+fn sort_presorted<T: SortElement>(data: &mut [T]) -> bool {
+    let len = data.len();
+    let mut i = 1;
+    while i < len && !data[i - 1].less(&data[i]) && !data[i].less(&data[i - 1]) {
+        i += 1;
+    }
+    if i >= len {
+        return true;
+    }
+
+    if data[i].less(&data[i - 1]) {
+        while i < len && !data[i - 1].less(&data[i]) {
+            i += 1;
+        }
+        if i < len {
+            return false;
+        }
+        data.reverse();
+        return true;
+    }
+
+    partial_insertion_sort(data, PRESORTED_DESCENT_LIMIT, len / PRESORTED_MOVE_RATIO)
+}
+
+/// `mysort.c` の `try_counting_sort` と同じ条件で計数ソートする。
+/// `int` と違ってキーから値に戻せないので、キーごとの書き込み位置を数えてから作業用の配列に置く。
+fn try_counting_sort<T: SortElement>(data: &mut [T]) -> bool {
+    let min = data.iter().map(T::radix_key).min().unwrap();
+    let max = data.iter().map(T::radix_key).max().unwrap();
+
+    let span = match (max - min).checked_add(1) {
+        Some(span) if span <= crate::BUCKET_SORT_ELEMENT_SIZE as u64 => span as usize,
+        Some(span) if span <= data.len() as u64 * crate::COUNTING_SORT_SPAN_RATIO as u64 => {
+            span as usize
+        }
+        _ => return false,
+    };
+
+    let mut offsets = vec![0; span + 1];
+    for value in data.iter() {
+        offsets[(value.radix_key() - min) as usize + 1] += 1;
+    }
+    for i in 1..offsets.len() {
+        offsets[i] += offsets[i - 1];
+    }
+
+    let values = data.to_vec();
+    for value in values {
+        let offset = &mut offsets[(value.radix_key() - min) as usize];
+        data[*offset] = value;
+        *offset += 1;
+    }
+    true
+}
+
+/// `mysort.c` の `radix_sort`。`radix_key` の下位の桁から 8bit ずつ安定な計数ソートを繰り返す。
+fn radix_sort<T: SortElement>(data: &mut [T]) {
+    let digit = |key: u64, pass: usize| {
+        (key >> (pass as u32 * RADIX_SORT_BITS)) as usize & (RADIX_SORT_BUCKETS - 1)
+    };
+    let passes = (T::RADIX_BITS / RADIX_SORT_BITS) as usize;
+
+    // 全てのパスのヒストグラムを 1 回の走査でまとめて作る
+    let mut counts = vec![[0; RADIX_SORT_BUCKETS]; passes];
+    for value in data.iter() {
+        let key = value.radix_key();
+        for (pass, count) in counts.iter_mut().enumerate() {
+            count[digit(key, pass)] += 1;
+        }
+    }
+
+    let mut buffer = data.to_vec();
+    let mut in_buffer = false;
+    for (pass, count) in counts.iter().enumerate() {
+        let (src, dst) = if in_buffer {
+            (&buffer[..], &mut data[..])
+        } else {
+            (&data[..], &mut buffer[..])
+        };
+
+        // 全要素でこの桁が同じなら並びは変わらないので飛ばす
+        if count[digit(src[0].radix_key(), pass)] == src.len() {
+            continue;
+        }
+
+        let mut offsets = [0; RADIX_SORT_BUCKETS];
+        let mut sum = 0;
+        for (offset, &count) in offsets.iter_mut().zip(count) {
+            *offset = sum;
+            sum += count;
+        }
+
+        for value in src {
+            let offset = &mut offsets[digit(value.radix_key(), pass)];
+            dst[*offset] = *value;
+            *offset += 1;
+        }
+
+        in_buffer = !in_buffer;
+    }
+
+    if in_buffer {
+        data.copy_from_slice(&buffer);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        rand::{seq::SliceRandom, thread_rng, Rng},
+    };
+
+    #[test]
+    fn generic_test() {
+        fn check<T: SortElement + std::fmt::Debug>(
+            data: &[T],
+            sort_by: impl Fn(&mut [T]),
+            eq: impl Fn(&T, &T) -> bool,
+        ) {
+            let mut origin = data.to_vec();
+            sort_by(&mut origin);
+
+            if T::RADIX_BITS > 0 {
+                for pair in origin.windows(2) {
+                    let (a, b) = (&pair[0], &pair[1]);
+                    assert_eq!(a.less(b), a.radix_key() < b.radix_key(), "{a:?} {b:?}");
+                }
+            }
+
+            let mut reversed = origin.clone();
+            reversed.reverse();
+
+            for (sort_fn, data) in [
+                (insertion_sort::<T> as fn(&mut [T]), data),
+                (heapsort::<T>, data),
+                (introsort::<T>, data),
+                (mysort::<T>, data),
+                // sort_presorted で処理される入力
+                (mysort::<T>, &origin),
+                (mysort::<T>, &reversed),
+            ] {
+                let mut data = data.to_vec();
+                sort_fn(&mut data);
+                assert!(
+                    data.iter().zip(&origin).all(|(a, b)| eq(a, b)),
+                    "{data:?} != {origin:?}"
+                );
+            }
+        }
+
+        macro_rules! check_int {
+            ($($ty:ty),+) => {$(
+                let mut rng = thread_rng();
+                for len in [0, 1, 2, 10, 100, 1000, 10_000] {
+                    let data = (0..len).map(|_| rng.gen::<$ty>()).collect::<Vec<_>>();
+                    check(&data, |d| d.sort_unstable(), |a, b| a == b);
+                    let data = (0..len).map(|_| rng.gen::<$ty>() % 10).collect::<Vec<_>>();
+                    check(&data, |d| d.sort_unstable(), |a, b| a == b);
+                }
+            )+};
+        }
+        check_int!(i8, u8, i16, u16, i32, u32, i64, u64, i128, u128, isize, usize);
+
+        macro_rules! check_float {
+            ($($ty:ident),+) => {$(
+                let mut rng = thread_rng();
+                let special = [
+                    $ty::NAN,
+                    -$ty::NAN,
+                    $ty::INFINITY,
+                    $ty::NEG_INFINITY,
+                    0.0,
+                    -0.0,
+                    $ty::MIN_POSITIVE,
+                    $ty::MAX,
+                    $ty::MIN,
+                ];
+                for len in [0, 1, 2, 10, 100, 1000, 10_000] {
+                    let data = (0..len)
+                        .map(|_| {
+                            if rng.gen_ratio(1, 10) {
+                                *special.choose(&mut rng).unwrap()
+                            } else {
+                                rng.gen_range(-1000.0..1000.0)
+                            }
+                        })
+                        .collect::<Vec<$ty>>();
+                    // NaN 同士も比較できるようにビット列で比べる
+                    check(
+                        &data,
+                        |d| d.sort_unstable_by(|a, b| a.total_cmp(b)),
+                        |a, b| a.to_bits() == b.to_bits(),
+                    );
+                }
+            )+};
+        }
+        check_float!(f32, f64);
+    }
+}
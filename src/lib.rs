@@ -1,4 +1,23 @@
-use std::ffi::c_int;
+use std::{
+    any::Any,
+    cmp::Ordering,
+    ffi::{c_int, c_void},
+    fmt,
+    marker::PhantomData,
+    ops::Range,
+    panic::{self, AssertUnwindSafe},
+    slice,
+};
+
+pub use {
+    parallel::{par_sort, par_sort_with_threads, sample_sort, sample_sort_with_threads},
+    simd::{simd_sort, simd_sort_with_kernel, Kernel},
+};
+
+pub mod external;
+pub mod generic;
+mod parallel;
+mod simd;
 
 macro_rules! ffi {
     ($($name:ident);+$(;)?) => {
@@ -20,6 +39,1137 @@ ffi! {
     quicksort;
     heapsort;
     insertion_sort;
-    bucket_sort;
+    small_sort;
+    introsort;
+    pdqsort;
+    dual_pivot_quicksort;
+    counting_sort;
+    radix_sort;
     mysort;
 }
+
+mod ffi {
+    use super::*;
+
+    extern "C" {
+        pub fn quicksort_with_pivot(ptr: *mut c_int, len: c_int, strategy: PivotStrategy);
+        pub fn bucket_sort(ptr: *mut c_int, len: c_int);
+        pub fn sorting_network(ptr: *mut c_int, len: c_int);
+        pub fn block_partition(ptr: *mut c_int, len: c_int, pivot: c_int) -> c_int;
+        pub fn three_way_partition(
+            ptr: *mut c_int,
+            len: c_int,
+            pivot: c_int,
+            lt: *mut c_int,
+            gt: *mut c_int,
+        );
+        pub fn introsort_with_partition(ptr: *mut c_int, len: c_int, scheme: PartitionScheme);
+        pub fn down_heap(ptr: *mut c_int, len: c_int, node: c_int);
+        pub fn select_nth(ptr: *mut c_int, len: c_int, k: c_int);
+        pub fn partial_sort(ptr: *mut c_int, len: c_int, k: c_int);
+        pub fn merge_sort_pairs(ptr: *mut KeyValue, len: c_int);
+        pub fn introsort_cmp(
+            base: *mut c_void,
+            len: c_int,
+            size: usize,
+            cmp: CompareFn,
+            context: *mut c_void,
+        );
+        pub fn heapsort_cmp(
+            base: *mut c_void,
+            len: c_int,
+            size: usize,
+            cmp: CompareFn,
+            context: *mut c_void,
+        );
+    }
+}
+
+/// `quicksort` の pivot の選び方。`mysort.c` の `enum pivot_strategy` と対応する。
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PivotStrategy {
+    /// 中央の要素。
+    /// 山型 (前半が昇順、後半が降順) の配列では毎回最大値を選ぶので O(n^2) になる。
+    Middle = 0,
+    /// 先頭・中央・末尾の中央値
+    MedianOfThree = 1,
+    /// 3 つの median-of-three の中央値 (Tukey's ninther)
+    Ninther = 2,
+    /// ランダムに選んだ要素
+    Random = 3,
+}
+
+impl PivotStrategy {
+    pub const ALL: [PivotStrategy; 4] = [
+        PivotStrategy::Middle,
+        PivotStrategy::MedianOfThree,
+        PivotStrategy::Ninther,
+        PivotStrategy::Random,
+    ];
+}
+
+/// pivot の選び方を指定して、heapsort へのフォールバックを持たない素の Quicksort でソートする。
+/// `quicksort` は `PivotStrategy::MedianOfThree` と同じ。
+pub fn quicksort_with_pivot(data: &mut [c_int], strategy: PivotStrategy) {
+    unsafe {
+        ffi::quicksort_with_pivot(data.as_mut_ptr(), data.len() as c_int, strategy);
+    }
+}
+
+/// `introsort` の分割方法。`mysort.c` の `enum partition_scheme` と対応する。
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PartitionScheme {
+    /// `block_partition` で pivot 以下と pivot 以上に分ける
+    Block = 0,
+    /// `three_way_partition` で pivot 未満、pivot と等しい、pivot より大きいの 3 つに分ける
+    ThreeWay = 1,
+}
+
+impl PartitionScheme {
+    pub const ALL: [PartitionScheme; 2] = [PartitionScheme::Block, PartitionScheme::ThreeWay];
+}
+
+/// 分割方法を指定して introsort でソートする。
+/// `introsort` は `PartitionScheme::Block` と同じ。
+pub fn introsort_with_partition(data: &mut [c_int], scheme: PartitionScheme) {
+    unsafe {
+        ffi::introsort_with_partition(data.as_mut_ptr(), data.len() as c_int, scheme);
+    }
+}
+
+/// `pivot` で `data` を分割し、分割位置を返す。
+/// 戻り値を `p` とすると `data[..p]` は全て `pivot` 以下、`data[p..]` は全て `pivot` 以上になる。
+///
+/// C の実装は `pivot` を番兵として使うため、`pivot` が `data` に含まれない場合は panic する。
+pub fn block_partition(data: &mut [c_int], pivot: c_int) -> usize {
+    assert!(
+        data.contains(&pivot),
+        "pivot {pivot} must be an element of data"
+    );
+    unsafe { ffi::block_partition(data.as_mut_ptr(), data.len() as c_int, pivot) as usize }
+}
+
+/// `pivot` で `data` を 3 つに分割し、`pivot` と等しい要素の範囲を返す。
+/// 戻り値を `lt..gt` とすると、`data[..lt]` は `pivot` 未満、`data[lt..gt]` は `pivot` と等しく、
+/// `data[gt..]` は `pivot` より大きい。`pivot` は `data` に含まれていなくてもよい。
+pub fn three_way_partition(data: &mut [c_int], pivot: c_int) -> Range<usize> {
+    let mut lt = 0;
+    let mut gt = 0;
+    unsafe {
+        ffi::three_way_partition(
+            data.as_mut_ptr(),
+            data.len() as c_int,
+            pivot,
+            &mut lt,
+            &mut gt,
+        );
+    }
+    lt as usize..gt as usize
+}
+
+/// `node` 以下の部分木が heap property (親 >= 子) を満たすように `node` を沈める。
+/// `node` の子孫がすでに heap property を満たしていることを前提とする。
+pub fn down_heap(data: &mut [c_int], node: usize) {
+    assert!(
+        node < data.len(),
+        "node {node} is out of range for length {}",
+        data.len()
+    );
+    unsafe {
+        ffi::down_heap(data.as_mut_ptr(), data.len() as c_int, node as c_int);
+    }
+}
+
+/// `data[k]` に `k` 番目 (0 始まり) に小さい要素を置き、
+/// `data[..k]` を `data[k]` 以下、`data[k + 1..]` を `data[k]` 以上にする。
+/// `slice::select_nth_unstable` と同じく、`k` が範囲外なら panic する。
+pub fn select_nth(data: &mut [c_int], k: usize) {
+    assert!(
+        k < data.len(),
+        "k {k} is out of range for length {}",
+        data.len()
+    );
+    unsafe {
+        ffi::select_nth(data.as_mut_ptr(), data.len() as c_int, k as c_int);
+    }
+}
+
+/// 小さい方から `k` 個をソートして `data[..k]` に置く。`data[k..]` の順序は不定。
+/// `k` が `data.len()` より大きい場合は全体をソートする。
+pub fn partial_sort(data: &mut [c_int], k: usize) {
+    let k = k.min(data.len());
+    unsafe {
+        ffi::partial_sort(data.as_mut_ptr(), data.len() as c_int, k as c_int);
+    }
+}
+
+/// `mysort.c` の `median_of_three`
+fn median_of_three(data: &[c_int], a: usize, b: usize, c: usize) -> usize {
+    if data[a] < data[b] {
+        if data[b] < data[c] {
+            return b;
+        }
+        return if data[a] < data[c] { c } else { a };
+    }
+    if data[a] < data[c] {
+        return a;
+    }
+    if data[b] < data[c] {
+        c
+    } else {
+        b
+    }
+}
+
+/// `mysort.c` の `SORTING_NETWORK_MAX`。`sorting_network` が扱える最大の要素数。
+pub const SORTING_NETWORK_MAX: usize = 16;
+
+/// 要素数ごとのソーティングネットワークでソートする。
+/// `data.len()` が `SORTING_NETWORK_MAX` を超える場合は panic する。
+pub fn sorting_network(data: &mut [c_int]) {
+    assert!(
+        data.len() <= SORTING_NETWORK_MAX,
+        "sorting network can sort at most {SORTING_NETWORK_MAX} elements, got {}",
+        data.len()
+    );
+    unsafe {
+        ffi::sorting_network(data.as_mut_ptr(), data.len() as c_int);
+    }
+}
+
+/// `mysort.c` の `BUCKET_SORT_ELEMENT_SIZE`。`bucket_sort` は `0..BUCKET_SORT_ELEMENT_SIZE` の値しか扱えない。
+pub const BUCKET_SORT_ELEMENT_SIZE: c_int = 1024;
+
+/// `bucket_sort` に範囲外の値が渡されたことを表す。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BucketSortError {
+    /// 最初に見つかった範囲外の要素の位置
+    pub index: usize,
+    /// その要素の値
+    pub value: c_int,
+}
+
+impl fmt::Display for BucketSortError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "data[{}] = {} is out of range 0..{BUCKET_SORT_ELEMENT_SIZE}",
+            self.index, self.value
+        )
+    }
+}
+
+impl std::error::Error for BucketSortError {}
+
+/// 全ての要素が `0..BUCKET_SORT_ELEMENT_SIZE` に収まっているか確認してから `bucket_sort` でソートする。
+/// 範囲外の要素があった場合は `data` に触れずにエラーを返す。
+pub fn bucket_sort(data: &mut [c_int]) -> Result<(), BucketSortError> {
+    if let Some((index, &value)) = data
+        .iter()
+        .enumerate()
+        .find(|(_, value)| !(0..BUCKET_SORT_ELEMENT_SIZE).contains(value))
+    {
+        return Err(BucketSortError { index, value });
+    }
+    unsafe { bucket_sort_unchecked(data) };
+    Ok(())
+}
+
+/// 範囲の確認をせずに `bucket_sort` でソートする。ベンチマーク用。
+///
+/// # Safety
+///
+/// `data` の全ての要素が `0..BUCKET_SORT_ELEMENT_SIZE` に収まっていなければならない。
+/// 範囲外の要素があるとスタック上のバッファの外に書き込む。
+pub unsafe fn bucket_sort_unchecked(data: &mut [c_int]) {
+    ffi::bucket_sort(data.as_mut_ptr(), data.len() as c_int);
+}
+
+/// キーと付随するデータの組。`mysort.c` の `struct key_value` と同じレイアウトを持つ。
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct KeyValue {
+    pub key: c_int,
+    pub payload: u32,
+}
+
+/// `key` でソートする。キーが等しい要素の順番は保たれる (安定ソート)。
+/// 既にソートされている部分を見つけてマージする、TimSort 風のマージソート。
+pub fn merge_sort_pairs(data: &mut [KeyValue]) {
+    unsafe {
+        ffi::merge_sort_pairs(data.as_mut_ptr(), data.len() as c_int);
+    }
+}
+
+/// `mysort.c` の `compare_fn`
+type CompareFn = unsafe extern "C" fn(*const c_void, *const c_void, *mut c_void) -> c_int;
+
+/// C の比較関数の `context` として渡すクロージャ。
+/// クロージャが panic した場合は C の中で unwind させずに payload を保存しておき、
+/// C から戻った後で panic を再開する。
+struct Comparator<T, F> {
+    compare: F,
+    panic: Option<Box<dyn Any + Send>>,
+    _marker: PhantomData<fn(&T, &T)>,
+}
+
+impl<T, F: FnMut(&T, &T) -> Ordering> Comparator<T, F> {
+    fn new(compare: F) -> Self {
+        Self {
+            compare,
+            panic: None,
+            _marker: PhantomData,
+        }
+    }
+
+    unsafe extern "C" fn call(a: *const c_void, b: *const c_void, context: *mut c_void) -> c_int {
+        let this = &mut *(context as *mut Self);
+        // 一度 panic したら残りの比較は適当に済ませる
+        if this.panic.is_some() {
+            return 0;
+        }
+        let (a, b) = (&*(a as *const T), &*(b as *const T));
+        match panic::catch_unwind(AssertUnwindSafe(|| (this.compare)(a, b))) {
+            Ok(ordering) => ordering as c_int,
+            Err(payload) => {
+                this.panic = Some(payload);
+                0
+            }
+        }
+    }
+
+    fn sort(
+        mut self,
+        data: &mut [T],
+        sort_fn: unsafe extern "C" fn(*mut c_void, c_int, usize, CompareFn, *mut c_void),
+    ) {
+        unsafe {
+            sort_fn(
+                data.as_mut_ptr() as *mut c_void,
+                data.len() as c_int,
+                std::mem::size_of::<T>(),
+                Self::call,
+                &mut self as *mut Self as *mut c_void,
+            );
+        }
+        if let Some(payload) = self.panic {
+            panic::resume_unwind(payload);
+        }
+    }
+}
+
+/// `compare` で比較して introsort でソートする。`qsort` のように任意の型の要素を扱える。
+///
+/// `compare` が全順序になっていなくても未定義動作にはならないが、結果の順番は不定になる。
+/// `compare` が panic した場合、`data` は何らかの順番に並び替えられた状態で panic が伝播する。
+pub fn introsort_by<T, F: FnMut(&T, &T) -> Ordering>(data: &mut [T], compare: F) {
+    Comparator::new(compare).sort(data, ffi::introsort_cmp);
+}
+
+/// `compare` で比較して heapsort でソートする。`introsort_by` を参照。
+pub fn heapsort_by<T, F: FnMut(&T, &T) -> Ordering>(data: &mut [T], compare: F) {
+    Comparator::new(compare).sort(data, ffi::heapsort_cmp);
+}
+
+/// `mysort.c` の `COUNTING_SORT_SPAN_RATIO`
+const COUNTING_SORT_SPAN_RATIO: i64 = 2;
+
+/// `mysort.c` の `try_counting_sort` と同じ条件で、計数ソートを使うべきなら値の範囲の大きさを返す
+fn counting_sort_span(min: c_int, max: c_int, len: usize) -> Option<usize> {
+    let span = max as i64 - min as i64 + 1;
+    (span <= BUCKET_SORT_ELEMENT_SIZE as i64 || span <= len as i64 * COUNTING_SORT_SPAN_RATIO)
+        .then_some(span as usize)
+}
+
+/// `data` をソートしたときの並び順を、元の位置の列として返す。
+/// 戻り値を `indices` とすると `indices.iter().map(|&i| data[i])` が昇順になる。
+/// 値が等しい要素は元の位置の順に並ぶ (安定)。
+///
+/// 値の範囲が狭ければ `counting_sort` と同じ条件で計数ソートを使い、
+/// そうでなければ値と位置を 1 つの `u64` にまとめて `generic::introsort` でソートする。
+pub fn argsort(data: &[c_int]) -> Vec<usize> {
+    let (Some(&min), Some(&max)) = (data.iter().min(), data.iter().max()) else {
+        return vec![];
+    };
+
+    if let Some(span) = counting_sort_span(min, max, data.len()) {
+        return counting_argsort(data, min, span);
+    }
+
+    // 上位 32bit に値、下位 32bit に位置を置くと、値が等しい要素は位置の順に並ぶ。
+    // 符号ビットを反転すると負の値も unsigned として大小関係を保てる。
+    assert!(
+        data.len() <= u32::MAX as usize + 1,
+        "argsort supports at most 2^32 elements"
+    );
+    let mut keys = data
+        .iter()
+        .enumerate()
+        .map(|(i, &value)| ((value as u32 ^ 0x8000_0000) as u64) << 32 | i as u64)
+        .collect::<Vec<_>>();
+    generic::introsort(&mut keys);
+    keys.into_iter().map(|key| key as u32 as usize).collect()
+}
+
+/// 各値の個数を数えて、値ごとの書き込み位置に前から順に位置を置く
+fn counting_argsort(data: &[c_int], min: c_int, span: usize) -> Vec<usize> {
+    let mut offsets = vec![0; span + 1];
+    for &value in data {
+        offsets[(value as i64 - min as i64) as usize + 1] += 1;
+    }
+    for i in 1..offsets.len() {
+        offsets[i] += offsets[i - 1];
+    }
+
+    let mut indices = vec![0; data.len()];
+    for (i, &value) in data.iter().enumerate() {
+        let offset = &mut offsets[(value as i64 - min as i64) as usize];
+        indices[*offset] = i;
+        *offset += 1;
+    }
+    indices
+}
+
+/// `mysort.c` の `INSERTION_SORT_THRESHOLD`。Rust 側の introsort もこれより小さい範囲を挿入ソートでソートする。
+pub(crate) const INSERTION_SORT_THRESHOLD: usize = 55;
+
+/// `mysort.c` の `SMALL_SORT_THRESHOLD`。`mysort` はこれ以下の大きさの配列を `small_sort` でソートする。
+/// `sort_pairs` は `KeyValue` に使えるソーティングネットワークがないので、
+/// 同じ大きさまでを `generic::insertion_sort` でソートする。
+const SMALL_SORT_THRESHOLD: usize = 65;
+
+/// `sort_pairs` の中で `KeyValue` を `key` だけで比べるためのラッパー。
+/// `key` が等しい組を等しいとみなすので、`KeyValue` 自体には `SortElement` を実装しない。
+#[repr(transparent)]
+#[derive(Clone, Copy)]
+struct ByKey(KeyValue);
+
+impl generic::SortElement for ByKey {
+    #[inline]
+    fn less(&self, other: &Self) -> bool {
+        self.0.key < other.0.key
+    }
+}
+
+fn by_key(data: &mut [KeyValue]) -> &mut [ByKey] {
+    // ByKey は repr(transparent) なので KeyValue と同じレイアウト
+    unsafe { slice::from_raw_parts_mut(data.as_mut_ptr() as *mut ByKey, data.len()) }
+}
+
+/// `key` でソートする。`mysort` と同じ方針で、
+/// 小さい配列は挿入ソート、キーの範囲が狭ければ計数ソート、それ以外は introsort を使う。
+/// 安定ではない (計数ソートを使った場合のみ安定になる)。
+pub fn sort_pairs(data: &mut [KeyValue]) {
+    if data.len() <= SMALL_SORT_THRESHOLD {
+        generic::insertion_sort(by_key(data));
+        return;
+    }
+
+    let min = data.iter().map(|x| x.key).min().unwrap();
+    let max = data.iter().map(|x| x.key).max().unwrap();
+    if let Some(span) = counting_sort_span(min, max, data.len()) {
+        counting_sort_pairs(data, min, span);
+        return;
+    }
+
+    generic::introsort(by_key(data));
+}
+
+/// キーごとの書き込み位置を数えてから、作業用の配列に前から順に置く
+fn counting_sort_pairs(data: &mut [KeyValue], min: c_int, span: usize) {
+    let mut offsets = vec![0; span + 1];
+    for pair in data.iter() {
+        offsets[(pair.key as i64 - min as i64) as usize + 1] += 1;
+    }
+    for i in 1..offsets.len() {
+        offsets[i] += offsets[i - 1];
+    }
+
+    let mut sorted = vec![KeyValue::default(); data.len()];
+    for &pair in data.iter() {
+        let offset = &mut offsets[(pair.key as i64 - min as i64) as usize];
+        sorted[*offset] = pair;
+        *offset += 1;
+    }
+    data.copy_from_slice(&sorted);
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        pretty_assertions::assert_eq,
+        rand::{rngs::ThreadRng, seq::SliceRandom, thread_rng, Rng},
+    };
+
+    /// fuzz テストの試行回数
+    pub(crate) const FUZZ_ITERATIONS: usize = 3000;
+
+    /// `generate` で作った入力を `sort_fn` でソートし、`sort_unstable` と同じ結果になることを
+    /// `iterations` 回確かめる。
+    pub(crate) fn fuzz_sort(
+        iterations: usize,
+        sort_fn: impl Fn(&mut [c_int]),
+        mut generate: impl FnMut(&mut ThreadRng) -> Vec<c_int>,
+    ) {
+        let mut rng = thread_rng();
+        for _ in 0..iterations {
+            let mut data = generate(&mut rng);
+            let mut origin = data.clone();
+            origin.sort_unstable();
+
+            sort_fn(&mut data);
+
+            assert_eq!(data, origin);
+        }
+    }
+
+    /// bench の `generic_test_sort` と同じく、小さな入力と 3000 要素のランダムな入力でソートを確かめる。
+    fn check_sort(sort_fn: impl Fn(&mut [c_int])) {
+        macro_rules! sort {
+            ($array:expr) => {{
+                let mut a = $array;
+                sort_fn(&mut a);
+                a
+            }};
+        }
+
+        let empty: [c_int; 0] = []; // type inference fails
+        assert_eq!(sort!(empty), empty);
+
+        assert_eq!(sort!([0]), [0]);
+        assert_eq!(sort!([1, 2]), [1, 2]);
+        assert_eq!(sort!([5, 8, 9, 3, 5]), [3, 5, 5, 8, 9]);
+        assert_eq!(
+            sort!([10, 9, 8, 7, 6, 5, 4, 3, 2, 1]),
+            [1, 2, 3, 4, 5, 6, 7, 8, 9, 10]
+        );
+
+        fuzz_sort(FUZZ_ITERATIONS, sort_fn, |rng| {
+            let mut data = vec![0; 3000];
+            rng.fill(data.as_mut_slice());
+            data
+        });
+    }
+
+    #[test]
+    fn introsort_test() {
+        check_sort(introsort);
+    }
+
+    #[test]
+    fn block_partition_test() {
+        let mut rng = thread_rng();
+
+        // fuzz
+        for _ in 0..3000 {
+            let len = rng.gen_range(1..3000);
+            let modulo = *[2, 100, c_int::MAX].choose(&mut rng).unwrap();
+            let mut data = (0..len)
+                .map(|_| rng.gen_range(0..modulo))
+                .collect::<Vec<c_int>>();
+            let pivot = data[rng.gen_range(0..len)];
+
+            let mut origin = data.clone();
+            let partition = block_partition(&mut data, pivot);
+
+            assert!(partition <= len);
+            assert!(data[..partition].iter().all(|&x| x <= pivot));
+            assert!(data[partition..].iter().all(|&x| x >= pivot));
+
+            // 要素の入れ替えのみが行われていること
+            let mut partitioned = data.clone();
+            partitioned.sort_unstable();
+            origin.sort_unstable();
+            assert_eq!(partitioned, origin);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn block_partition_rejects_missing_pivot() {
+        block_partition(&mut [1, 2, 3], 4);
+    }
+
+    #[test]
+    fn down_heap_test() {
+        fn is_heap(data: &[c_int]) -> bool {
+            (1..data.len()).all(|i| data[(i - 1) / 2] >= data[i])
+        }
+
+        assert_eq!(
+            {
+                let mut a = [1, 5, 4, 3, 2];
+                down_heap(&mut a, 0);
+                a
+            },
+            [5, 3, 4, 1, 2]
+        );
+
+        let mut rng = thread_rng();
+
+        // fuzz
+        for _ in 0..3000 {
+            let mut data = (0..rng.gen_range(1..3000)).collect::<Vec<c_int>>();
+            rng.fill(data.as_mut_slice());
+
+            // 葉から順に沈めるとヒープになる
+            for node in (0..data.len() / 2).rev() {
+                down_heap(&mut data, node);
+            }
+            assert!(is_heap(&data));
+
+            // 根を書き換えても 1 回の down_heap でヒープに戻る
+            data[0] = rng.gen();
+            down_heap(&mut data, 0);
+            assert!(is_heap(&data));
+        }
+    }
+
+    #[test]
+    fn counting_sort_test() {
+        check_sort(counting_sort);
+
+        // 負の値やずれた範囲、計数ソートできない広い範囲を混ぜる
+        fn generate(rng: &mut ThreadRng) -> Vec<c_int> {
+            let len = rng.gen_range(0..3000);
+            let (start, span) = *[
+                (0, BUCKET_SORT_ELEMENT_SIZE),
+                (-500, 1000),
+                (5000, 1000),
+                (c_int::MIN, 100),
+                (c_int::MAX - 100, 100),
+                (-1_000_000, 2_000_000),
+            ]
+            .choose(rng)
+            .unwrap();
+            (0..len).map(|_| start + rng.gen_range(0..span)).collect()
+        }
+        fuzz_sort(FUZZ_ITERATIONS, counting_sort, generate);
+        fuzz_sort(FUZZ_ITERATIONS, mysort, generate);
+
+        // 最後の要素だけが [0, BUCKET_SORT_ELEMENT_SIZE) に収まらない
+        let mut data = (0..3000)
+            .map(|i| i % 1000)
+            .chain([-1])
+            .collect::<Vec<c_int>>();
+        let mut expected = data.clone();
+        expected.sort_unstable();
+        counting_sort(&mut data);
+        assert_eq!(data, expected);
+    }
+
+    #[test]
+    fn radix_sort_test() {
+        check_sort(radix_sort);
+
+        let mut data = [c_int::MAX, -1, 0, c_int::MIN, 1, c_int::MIN + 1, -256, 256];
+        radix_sort(&mut data);
+        assert_eq!(
+            data,
+            [c_int::MIN, c_int::MIN + 1, -256, -1, 0, 1, 256, c_int::MAX]
+        );
+
+        let mut rng = thread_rng();
+
+        // 一部の桁が全要素で同じ場合
+        for mask in [0x0000_00ff, 0x00ff_ff00, 0x7f00_0000, -0x0100_0000] {
+            let mut data = (0..3000)
+                .map(|_| rng.gen::<c_int>() & mask)
+                .collect::<Vec<c_int>>();
+            let mut origin = data.clone();
+            origin.sort_unstable();
+
+            radix_sort(&mut data);
+            assert_eq!(data, origin);
+        }
+    }
+
+    #[test]
+    fn merge_sort_pairs_test() {
+        fn tagged(keys: impl IntoIterator<Item = c_int>) -> Vec<KeyValue> {
+            keys.into_iter()
+                .enumerate()
+                .map(|(i, key)| KeyValue {
+                    key,
+                    payload: i as u32,
+                })
+                .collect()
+        }
+
+        fn check(mut data: Vec<KeyValue>) {
+            let mut origin = data.clone();
+            // std の sort_by_key は安定なので結果は一意に決まる
+            origin.sort_by_key(|x| x.key);
+            merge_sort_pairs(&mut data);
+            assert_eq!(data, origin);
+        }
+
+        check(vec![]);
+        check(tagged([0]));
+        check(tagged([2, 1, 2, 1, 2, 1]));
+        // 等しいキーを含む降順の列は反転してはいけない
+        check(tagged([5, 5, 4, 4, 3, 3, 2, 2, 1, 1]));
+        check(tagged((0..1000).rev()));
+        check(tagged((0..1000).map(|x| x / 10)));
+        check(tagged((0..1000).map(|x| -(x / 10))));
+
+        let mut rng = thread_rng();
+
+        // fuzz
+        for _ in 0..FUZZ_ITERATIONS {
+            let len = rng.gen_range(0..3000);
+            let modulo = *[1, 10, 1000, c_int::MAX].choose(&mut rng).unwrap();
+            let mut keys = (0..len)
+                .map(|_| rng.gen_range(0..modulo))
+                .collect::<Vec<c_int>>();
+
+            // ランを含む入力
+            if rng.gen() {
+                for chunk in keys.chunks_mut(rng.gen_range(1..500)) {
+                    match rng.gen_range(0..3) {
+                        0 => chunk.sort_unstable(),
+                        1 => chunk.sort_unstable_by(|a, b| b.cmp(a)),
+                        _ => {}
+                    }
+                }
+            }
+
+            check(tagged(keys));
+        }
+    }
+
+    #[test]
+    fn mysort_presorted_test() {
+        let mut rng = thread_rng();
+
+        // 計数ソートできない値の範囲でも、整列済みの部分を利用してソートできること
+        for len in [16, 17, 65, 66, 1000, 10_000] {
+            let mut sorted = (0..len).map(|_| rng.gen::<c_int>()).collect::<Vec<c_int>>();
+            sorted.sort_unstable();
+
+            let mut reversed = sorted.clone();
+            reversed.reverse();
+            mysort(&mut reversed);
+            assert_eq!(reversed, sorted);
+
+            // 小さい値が後ろに、大きい値が前にずれている
+            let mut nearly_sorted = sorted.clone();
+            nearly_sorted.swap(len / 3, len / 3 + 5);
+            nearly_sorted[len - 3..].rotate_right(1);
+            nearly_sorted.rotate_left(1);
+            mysort(&mut nearly_sorted);
+            assert_eq!(nearly_sorted, sorted);
+
+            // 逆転は 1 箇所だけだが、要素を遠くまで動かさないといけない
+            let mut halves = sorted.clone();
+            halves.rotate_left(len / 2);
+            mysort(&mut halves);
+            assert_eq!(halves, sorted);
+
+            // 途中で諦めるほど崩れている
+            let mut shuffled = sorted.clone();
+            for i in (0..len).step_by(7) {
+                shuffled.swap(i, len - 1 - i);
+            }
+            mysort(&mut shuffled);
+            assert_eq!(shuffled, sorted);
+        }
+    }
+
+    #[test]
+    fn pdqsort_test() {
+        check_sort(pdqsort);
+
+        // pdqsort が特別扱いするパターン
+        let len = 100_000;
+        let patterns: Vec<Vec<c_int>> = vec![
+            (0..len).collect(),
+            (0..len).rev().collect(),
+            vec![7; len as usize],
+            (0..len).map(|x| x % 2).collect(),
+            // organ pipe
+            (0..len).map(|x| x.min(len - x)).collect(),
+            // sawtooth
+            (0..len).map(|x| x % 1000).collect(),
+            // 整列済みの末尾に 1 つだけ小さい値
+            (1..len).chain([0]).collect(),
+        ];
+        for mut data in patterns {
+            let mut origin = data.clone();
+            origin.sort_unstable();
+            pdqsort(&mut data);
+            assert_eq!(data, origin);
+        }
+    }
+
+    #[test]
+    fn introsort_partition_scheme_test() {
+        let mut rng = thread_rng();
+
+        for scheme in PartitionScheme::ALL {
+            check_sort(|d| introsort_with_partition(d, scheme));
+
+            // 重複の多い配列
+            for modulo in [1, 2, 1000] {
+                let mut data = (0..100_000)
+                    .map(|_| rng.gen_range(0..modulo))
+                    .collect::<Vec<c_int>>();
+                let mut origin = data.clone();
+                origin.sort_unstable();
+                introsort_with_partition(&mut data, scheme);
+                assert_eq!(data, origin);
+            }
+        }
+    }
+
+    #[test]
+    fn three_way_partition_test() {
+        let mut data = [3, 1, 2, 3, 5, 3, 0, 4];
+        let equal = three_way_partition(&mut data, 3);
+        assert_eq!(equal, 3..6);
+        assert_eq!(data[equal], [3, 3, 3]);
+
+        let mut rng = thread_rng();
+
+        // fuzz
+        for _ in 0..FUZZ_ITERATIONS {
+            let len = rng.gen_range(0..3000);
+            let modulo = *[1, 2, 100, c_int::MAX].choose(&mut rng).unwrap();
+            let mut data = (0..len)
+                .map(|_| rng.gen_range(0..modulo))
+                .collect::<Vec<c_int>>();
+            // data に含まれない pivot も試す
+            let pivot = if len > 0 && rng.gen() {
+                data[rng.gen_range(0..len)]
+            } else {
+                rng.gen_range(0..modulo)
+            };
+
+            let mut origin = data.clone();
+            let equal = three_way_partition(&mut data, pivot);
+
+            assert!(data[..equal.start].iter().all(|&x| x < pivot));
+            assert!(data[equal.clone()].iter().all(|&x| x == pivot));
+            assert!(data[equal.end..].iter().all(|&x| x > pivot));
+
+            let mut partitioned = data.clone();
+            partitioned.sort_unstable();
+            origin.sort_unstable();
+            assert_eq!(partitioned, origin);
+        }
+    }
+
+    #[test]
+    fn dual_pivot_quicksort_test() {
+        check_sort(dual_pivot_quicksort);
+
+        let mut rng = thread_rng();
+
+        // 2 つの pivot が等しくなりやすい配列
+        for modulo in [1, 2, 3, 1000] {
+            let mut data = (0..100_000)
+                .map(|_| rng.gen_range(0..modulo))
+                .collect::<Vec<c_int>>();
+            let mut origin = data.clone();
+            origin.sort_unstable();
+            dual_pivot_quicksort(&mut data);
+            assert_eq!(data, origin);
+        }
+    }
+
+    #[test]
+    fn small_sort_test() {
+        check_sort(small_sort);
+    }
+
+    #[test]
+    fn sorting_network_test() {
+        // 0-1 principle:
+        // 0 と 1 だけからなる全ての入力をソートできるネットワークは、任意の入力をソートできる
+        for len in 0..=SORTING_NETWORK_MAX {
+            for bits in 0..1u32 << len {
+                let mut data = (0..len)
+                    .map(|i| (bits >> i & 1) as c_int)
+                    .collect::<Vec<_>>();
+                sorting_network(&mut data);
+
+                let zeros = len - bits.count_ones() as usize;
+                assert!(data[..zeros].iter().all(|&x| x == 0), "{len} {bits:b}");
+                assert!(data[zeros..].iter().all(|&x| x == 1), "{len} {bits:b}");
+            }
+        }
+
+        // INT_MAX や INT_MIN を含んでいても壊れない
+        let mut data = [c_int::MAX, 3, c_int::MIN, c_int::MAX, 0];
+        sorting_network(&mut data);
+        assert_eq!(data, [c_int::MIN, 0, 3, c_int::MAX, c_int::MAX]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn sorting_network_rejects_long_input() {
+        sorting_network(&mut [0; SORTING_NETWORK_MAX + 1]);
+    }
+
+    #[test]
+    fn sort_by_test() {
+        type SortBy<T> = fn(&mut [T], fn(&T, &T) -> Ordering);
+
+        #[derive(Clone, Debug, PartialEq)]
+        struct Record {
+            id: u64,
+            score: f64,
+            name: String,
+        }
+
+        let mut rng = thread_rng();
+
+        for sort_by in [introsort_by as SortBy<Record>, heapsort_by] {
+            // fuzz
+            for _ in 0..300 {
+                let len = rng.gen_range(0..3000);
+                let data = (0..len)
+                    .map(|_| Record {
+                        id: rng.gen_range(0..100),
+                        score: rng.gen(),
+                        name: rng.gen::<u16>().to_string(),
+                    })
+                    .collect::<Vec<_>>();
+
+                let mut origin = data.clone();
+                origin.sort_by(|a, b| a.id.cmp(&b.id).then(a.name.cmp(&b.name)));
+                let mut sorted = data.clone();
+                sort_by(&mut sorted, |a, b| {
+                    a.id.cmp(&b.id).then(a.name.cmp(&b.name))
+                });
+                // 同じキーの要素の順番は不定なのでキーだけ比べる
+                assert!(sorted
+                    .iter()
+                    .zip(&origin)
+                    .all(|(a, b)| a.id == b.id && a.name == b.name));
+
+                let mut origin = data.clone();
+                origin.sort_by(|a, b| a.score.total_cmp(&b.score));
+                let mut sorted = data;
+                sort_by(&mut sorted, |a, b| a.score.total_cmp(&b.score));
+                assert_eq!(sorted, origin);
+            }
+        }
+    }
+
+    #[test]
+    fn sort_by_inconsistent_comparator_test() {
+        let mut rng = thread_rng();
+
+        // 全順序になっていない比較関数でも、要素の並び替えにしかならないこと
+        for _ in 0..100 {
+            let len = rng.gen_range(0..3000);
+            let origin = (0..len).map(|x| x.to_string()).collect::<Vec<_>>();
+
+            let mut data = origin.clone();
+            introsort_by(&mut data, |_, _| {
+                *[Ordering::Less, Ordering::Equal, Ordering::Greater]
+                    .choose(&mut thread_rng())
+                    .unwrap()
+            });
+            data.sort();
+            let mut expected = origin.clone();
+            expected.sort();
+            assert_eq!(data, expected);
+
+            let mut data = origin.clone();
+            heapsort_by(&mut data, |_, _| Ordering::Less);
+            data.sort();
+            assert_eq!(data, expected);
+        }
+    }
+
+    #[test]
+    fn sort_by_panic_test() {
+        let origin = (0..1000).map(|x| x.to_string()).collect::<Vec<_>>();
+        let mut data = origin.clone();
+        data.reverse();
+
+        let mut count = 0;
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            introsort_by(&mut data, |a, b| {
+                count += 1;
+                if count == 500 {
+                    panic!("comparator panicked");
+                }
+                a.cmp(b)
+            })
+        }));
+        assert_eq!(
+            *result.unwrap_err().downcast::<&str>().unwrap(),
+            "comparator panicked"
+        );
+
+        // panic しても要素は失われない
+        data.sort();
+        let mut expected = origin;
+        expected.sort();
+        assert_eq!(data, expected);
+    }
+
+    #[test]
+    fn argsort_test() {
+        assert_eq!(argsort(&[]), [] as [usize; 0]);
+        assert_eq!(argsort(&[5]), [0]);
+        assert_eq!(argsort(&[3, 1, 2, 1]), [1, 3, 2, 0]);
+        assert_eq!(argsort(&[c_int::MAX, 0, c_int::MIN, -1]), [2, 3, 1, 0]);
+
+        let mut rng = thread_rng();
+
+        // fuzz
+        for _ in 0..FUZZ_ITERATIONS {
+            let len = rng.gen_range(0..3000);
+            // 計数ソートを使う範囲と使わない範囲を混ぜる
+            let (start, span) = *[
+                (0, BUCKET_SORT_ELEMENT_SIZE),
+                (-500, 10),
+                (5000, 5000),
+                (c_int::MIN, 100),
+                (c_int::MIN, c_int::MAX),
+            ]
+            .choose(&mut rng)
+            .unwrap();
+            let data = (0..len)
+                .map(|_| start + rng.gen_range(0..span))
+                .collect::<Vec<c_int>>();
+
+            // sort_by_key は安定なので結果は一意に決まる
+            let mut expected = (0..len).collect::<Vec<usize>>();
+            expected.sort_by_key(|&i| data[i]);
+
+            assert_eq!(argsort(&data), expected);
+        }
+    }
+
+    #[test]
+    fn sort_pairs_test() {
+        let mut rng = thread_rng();
+
+        // fuzz
+        for _ in 0..FUZZ_ITERATIONS {
+            // 挿入ソート、計数ソート、introsort のそれぞれを通る大きさと範囲
+            let len = *[10, 65, 66, 1000, 5000].choose(&mut rng).unwrap();
+            let (start, span) = *[(0, 1000), (-5000, 3000), (c_int::MIN, c_int::MAX)]
+                .choose(&mut rng)
+                .unwrap();
+            let data = (0..len)
+                .map(|_| KeyValue {
+                    key: start + rng.gen_range(0..span),
+                    payload: rng.gen(),
+                })
+                .collect::<Vec<_>>();
+
+            let mut expected = data.clone();
+            expected.sort_by_key(|x| x.key);
+
+            let mut sorted = data;
+            sort_pairs(&mut sorted);
+
+            // 安定ではないので、キーの列と要素の集合を比べる
+            assert!(sorted
+                .iter()
+                .map(|x| x.key)
+                .eq(expected.iter().map(|x| x.key)));
+            sorted.sort_by_key(|x| (x.key, x.payload));
+            expected.sort_by_key(|x| (x.key, x.payload));
+            assert_eq!(sorted, expected);
+        }
+    }
+
+    #[test]
+    fn select_nth_test() {
+        let mut rng = thread_rng();
+
+        // fuzz
+        for _ in 0..FUZZ_ITERATIONS {
+            let len = rng.gen_range(1..3000);
+            // 重複が多い場合と少ない場合の両方を試す
+            let max = *[2, 100, c_int::MAX].choose(&mut rng).unwrap();
+            let mut data = (0..len)
+                .map(|_| rng.gen_range(0..max))
+                .collect::<Vec<c_int>>();
+            if rng.gen_bool(0.1) {
+                data.sort();
+            }
+            let k = rng.gen_range(0..len);
+
+            let mut expected = data.clone();
+            expected.select_nth_unstable(k);
+
+            select_nth(&mut data, k);
+            assert_eq!(data[k], expected[k]);
+            assert!(data[..k].iter().all(|&x| x <= data[k]));
+            assert!(data[k + 1..].iter().all(|&x| x >= data[k]));
+
+            data.sort();
+            expected.sort();
+            assert_eq!(data, expected);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "out of range")]
+    fn select_nth_rejects_out_of_range() {
+        select_nth(&mut [1, 2, 3], 3);
+    }
+
+    #[test]
+    fn partial_sort_test() {
+        let mut rng = thread_rng();
+
+        // fuzz
+        for _ in 0..FUZZ_ITERATIONS {
+            let len = rng.gen_range(0..3000);
+            let mut data = vec![0; len];
+            rng.fill(data.as_mut_slice());
+            let k = rng.gen_range(0..=len + 1);
+
+            let mut expected = data.clone();
+            expected.sort();
+
+            partial_sort(&mut data, k);
+            let k = k.min(len);
+            assert_eq!(data[..k], expected[..k]);
+
+            data.sort();
+            assert_eq!(data, expected);
+        }
+    }
+
+    #[test]
+    fn quicksort_test() {
+        check_sort(quicksort);
+    }
+
+    #[test]
+    fn quicksort_pivot_strategy_test() {
+        for strategy in PivotStrategy::ALL {
+            check_sort(|d| quicksort_with_pivot(d, strategy));
+
+            // 重複が多い配列や整列済みの配列でも終了すること
+            let mut rng = thread_rng();
+            for len in [100, 1000, 10_000] {
+                for modulo in [1, 2, 10] {
+                    let mut data = (0..len)
+                        .map(|_| rng.gen_range(0..modulo))
+                        .collect::<Vec<c_int>>();
+                    let mut origin = data.clone();
+                    origin.sort_unstable();
+                    quicksort_with_pivot(&mut data, strategy);
+                    assert_eq!(data, origin);
+                }
+                let mut data = (0..len).collect::<Vec<c_int>>();
+                quicksort_with_pivot(&mut data, strategy);
+                assert_eq!(data, (0..len).collect::<Vec<c_int>>());
+            }
+        }
+    }
+}
@@ -0,0 +1,328 @@
+//! 複数のスレッドでソートする。
+
+use {
+    super::{ffi, median_of_three},
+    std::{
+        ffi::c_int,
+        sync::{Condvar, Mutex},
+        thread,
+    },
+};
+
+/// これより小さい範囲は分割せずに 1 つのスレッドで `introsort` する。
+/// スレッド間の受け渡しのコストが分割で得られる並列性を上回らない大きさ。
+const PAR_SORT_THRESHOLD: usize = 1 << 16;
+
+/// 未処理の範囲と、取り出されたがまだソートし終わっていない範囲の数
+struct QueueState<'a> {
+    tasks: Vec<(&'a mut [c_int], u32)>,
+    pending: usize,
+}
+
+/// ワーカー間で共有するキュー
+struct WorkQueue<'a> {
+    state: Mutex<QueueState<'a>>,
+    available: Condvar,
+}
+
+impl<'a> WorkQueue<'a> {
+    fn push(&self, task: &'a mut [c_int], recur_limit: u32) {
+        let mut state = self.state.lock().unwrap();
+        state.tasks.push((task, recur_limit));
+        state.pending += 1;
+        self.available.notify_one();
+    }
+
+    /// 範囲を 1 つ取り出す。全ての範囲がソートし終わったら `None` を返す。
+    fn pop(&self) -> Option<(&'a mut [c_int], u32)> {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if let Some(task) = state.tasks.pop() {
+                return Some(task);
+            }
+            if state.pending == 0 {
+                return None;
+            }
+            state = self.available.wait(state).unwrap();
+        }
+    }
+
+    fn done(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.pending -= 1;
+        if state.pending == 0 {
+            self.available.notify_all();
+        }
+    }
+
+    /// `threads` 個のスレッドで、キューが空になるまで範囲を取り出して `sort` する。
+    /// どれかのスレッドが panic しても他のスレッドは残りの範囲を処理して終わり、
+    /// その後で panic が呼び出し元に伝わる。
+    fn run(&self, threads: usize, sort: impl Fn(&Self, &'a mut [c_int], u32) + Sync) {
+        thread::scope(|scope| {
+            for _ in 0..threads {
+                scope.spawn(|| {
+                    while let Some((data, recur_limit)) = self.pop() {
+                        // panic しても drop で done を呼び、pending が 0 になるようにする
+                        let _done = Done(self);
+                        sort(self, data, recur_limit);
+                    }
+                });
+            }
+        });
+    }
+}
+
+/// drop したときに `WorkQueue::done` を呼ぶ
+struct Done<'q, 'a>(&'q WorkQueue<'a>);
+
+impl Drop for Done<'_, '_> {
+    fn drop(&mut self) {
+        self.0.done();
+    }
+}
+
+/// 利用できるコア数のスレッドで introsort する。
+pub fn par_sort(data: &mut [c_int]) {
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    par_sort_with_threads(data, threads);
+}
+
+/// `threads` 個のスレッドで introsort する。
+/// 大きい範囲を `block_partition` で分割し、片方をキューに入れて空いているスレッドに渡す。
+/// `PAR_SORT_THRESHOLD` 以下の範囲は `introsort` でソートする。
+pub fn par_sort_with_threads(data: &mut [c_int], threads: usize) {
+    assert!(threads > 0, "threads must be positive");
+
+    if threads == 1 || data.len() <= PAR_SORT_THRESHOLD {
+        super::introsort(data);
+        return;
+    }
+
+    let recur_limit = 2 * data.len().ilog2();
+    let queue = WorkQueue {
+        state: Mutex::new(QueueState {
+            tasks: vec![(data, recur_limit)],
+            pending: 1,
+        }),
+        available: Condvar::new(),
+    };
+
+    queue.run(threads, sort_task);
+}
+
+/// 小さい方をキューに入れ、大きい方は自分で分割し続ける。
+fn sort_task<'a>(queue: &WorkQueue<'a>, mut data: &'a mut [c_int], mut recur_limit: u32) {
+    while data.len() > PAR_SORT_THRESHOLD && recur_limit > 0 {
+        let len = data.len();
+        let pivot_index = median_of_three(data, 0, len / 2, len - 1);
+        data.swap(pivot_index, len / 2);
+        let pivot = data[len / 2];
+
+        let partition =
+            unsafe { ffi::block_partition(data.as_mut_ptr(), len as c_int, pivot) as usize };
+
+        // pivot が最小値だと片側が空になるので、introsort の heapsort に任せる
+        if partition == 0 || partition == len {
+            break;
+        }
+
+        recur_limit -= 1;
+        let (left, right) = data.split_at_mut(partition);
+        if left.len() < right.len() {
+            queue.push(left, recur_limit);
+            data = right;
+        } else {
+            queue.push(right, recur_limit);
+            data = left;
+        }
+    }
+
+    super::introsort(data);
+}
+
+/// これより小さい配列は分配せずに `mysort` する。
+const SAMPLE_SORT_THRESHOLD: usize = 1 << 16;
+
+/// スレッドあたりのバケットの数。多いほど各スレッドの仕事量が均等になる。
+const SAMPLE_SORT_BUCKETS_PER_THREAD: usize = 4;
+
+/// バケットあたりの標本の数。多いほど splitter が正確になる。
+const SAMPLE_SORT_OVERSAMPLING: usize = 32;
+
+/// 利用できるコア数のスレッドで sample sort する。
+pub fn sample_sort(data: &mut [c_int]) {
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    sample_sort_with_threads(data, threads);
+}
+
+/// https://en.wikipedia.org/wiki/Samplesort
+/// `threads` 個のスレッドで sample sort する。
+/// 標本から選んだ splitter で要素をバケットに分配し、各バケットを `mysort` でソートする。
+/// 分配とバケットのソートはどちらもスレッドごとに並列に行う。
+pub fn sample_sort_with_threads(data: &mut [c_int], threads: usize) {
+    assert!(threads > 0, "threads must be positive");
+
+    if threads == 1 || data.len() <= SAMPLE_SORT_THRESHOLD {
+        super::mysort(data);
+        return;
+    }
+
+    let splitters = choose_splitters(data, threads * SAMPLE_SORT_BUCKETS_PER_THREAD);
+    let buckets = splitters.len() + 1;
+
+    // スレッドごとに担当する範囲の要素をバケットに分ける
+    let chunk_len = (data.len() + threads - 1) / threads;
+    let distributed = thread::scope(|scope| {
+        data.chunks(chunk_len)
+            .map(|chunk| {
+                let splitters = &splitters;
+                scope.spawn(move || {
+                    let mut local = vec![Vec::new(); buckets];
+                    for &value in chunk {
+                        local[splitters.partition_point(|&s| s <= value)].push(value);
+                    }
+                    local
+                })
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect::<Vec<Vec<Vec<c_int>>>>()
+    });
+
+    // バケットごとに書き込む範囲を切り出す
+    let mut ranges = Vec::with_capacity(buckets);
+    let mut rest = data;
+    for bucket in 0..buckets {
+        let len = distributed.iter().map(|local| local[bucket].len()).sum();
+        let (range, tail) = rest.split_at_mut(len);
+        ranges.push((bucket, range));
+        rest = tail;
+    }
+
+    // 空いているスレッドがバケットを 1 つずつ取り出して書き戻し、ソートする
+    let ranges = Mutex::new(ranges);
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| loop {
+                let Some((bucket, range)) = ranges.lock().unwrap().pop() else {
+                    break;
+                };
+                let mut offset = 0;
+                for local in &distributed {
+                    range[offset..offset + local[bucket].len()].copy_from_slice(&local[bucket]);
+                    offset += local[bucket].len();
+                }
+                super::mysort(range);
+            });
+        }
+    });
+}
+
+/// 標本を `buckets * SAMPLE_SORT_OVERSAMPLING` 個取り出してソートし、
+/// 等間隔に `buckets - 1` 個の splitter を選ぶ。
+fn choose_splitters(data: &[c_int], buckets: usize) -> Vec<c_int> {
+    // https://en.wikipedia.org/wiki/Xorshift
+    let mut state = 2463534242u32;
+    let mut sample = (0..buckets * SAMPLE_SORT_OVERSAMPLING)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            data[state as usize % data.len()]
+        })
+        .collect::<Vec<_>>();
+    super::mysort(&mut sample);
+
+    (1..buckets)
+        .map(|i| sample[i * SAMPLE_SORT_OVERSAMPLING])
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        crate::test::fuzz_sort,
+        rand::{seq::SliceRandom, thread_rng, Rng},
+        std::{panic, sync::mpsc, time::Duration},
+    };
+
+    #[test]
+    fn work_queue_panic_test() {
+        // 1 つの範囲で panic しても他のスレッドが待ち続けず、残りの範囲を処理してから panic が伝わる
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let mut data = (0..64).collect::<Vec<c_int>>();
+            let queue = WorkQueue {
+                state: Mutex::new(QueueState {
+                    tasks: data.chunks_mut(8).map(|chunk| (chunk, 0)).collect(),
+                    pending: 8,
+                }),
+                available: Condvar::new(),
+            };
+            let result = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+                queue.run(4, |_, data, _| {
+                    if data[0] == 24 {
+                        panic!("injected panic");
+                    }
+                    data.fill(-1);
+                })
+            }));
+            drop(queue);
+            sender.send((result.is_err(), data)).unwrap();
+        });
+
+        let (panicked, data) = receiver
+            .recv_timeout(Duration::from_secs(10))
+            .expect("workers should not wait forever after a panic");
+        assert!(panicked);
+        assert_eq!(data[24..32], (24..32).collect::<Vec<_>>());
+        assert!(data[..24].iter().chain(&data[32..]).all(|&x| x == -1));
+    }
+
+    #[test]
+    fn par_sort_test() {
+        let sort_fn =
+            |data: &mut [c_int]| par_sort_with_threads(data, thread_rng().gen_range(1..=4));
+        fuzz_sort(30, sort_fn, |rng| {
+            // 並列に分割する閾値をまたぐ大きさ
+            let len = rng.gen_range(0..500_000);
+            let max = *[1000, c_int::MAX].choose(rng).unwrap();
+            let mut data = (0..len)
+                .map(|_| rng.gen_range(0..max))
+                .collect::<Vec<c_int>>();
+            if rng.gen_bool(0.2) {
+                data.sort();
+            }
+            data
+        });
+
+        let mut data = (0..200_000).rev().collect::<Vec<c_int>>();
+        par_sort(&mut data);
+        assert!(data.windows(2).all(|w| w[0] <= w[1]));
+    }
+
+    #[test]
+    fn sample_sort_test() {
+        let sort_fn =
+            |data: &mut [c_int]| sample_sort_with_threads(data, thread_rng().gen_range(1..=8));
+        fuzz_sort(30, sort_fn, |rng| {
+            let len = rng.gen_range(0..500_000);
+            // 重複が多いと splitter が重なり、空のバケットができる
+            let max = *[1, 1000, c_int::MAX].choose(rng).unwrap();
+            let mut data = (0..len)
+                .map(|_| rng.gen_range(0..max))
+                .collect::<Vec<c_int>>();
+            if rng.gen_bool(0.2) {
+                data.sort();
+            }
+            data
+        });
+
+        let mut data = (0..200_000).rev().collect::<Vec<c_int>>();
+        sample_sort(&mut data);
+        assert!(data.windows(2).all(|w| w[0] <= w[1]));
+    }
+}
@@ -0,0 +1,365 @@
+//! 分割と小さい配列のソートを AVX2 でベクトル化した introsort。
+//! AVX2 が使えるかどうかは実行時に調べ、使えない場合は `mysort.c` の関数を使う。
+
+use {
+    super::{ffi, median_of_three, INSERTION_SORT_THRESHOLD},
+    std::ffi::c_int,
+};
+
+/// 分割と小さい配列のソートに使う実装
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kernel {
+    /// `mysort.c` の `block_partition` と `small_sort`
+    Portable,
+    /// 8 要素ずつ比較する分割と、bitonic sort によるソーティングネットワーク
+    Avx2,
+}
+
+impl Kernel {
+    pub const ALL: [Kernel; 2] = [Kernel::Portable, Kernel::Avx2];
+
+    /// この CPU で使える中で最も速い実装を返す。
+    pub fn detect() -> Self {
+        if Self::Avx2.is_available() {
+            Self::Avx2
+        } else {
+            Self::Portable
+        }
+    }
+
+    /// この CPU で使えるかどうか
+    pub fn is_available(self) -> bool {
+        match self {
+            Self::Portable => true,
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            Self::Avx2 => is_x86_feature_detected!("avx2"),
+            #[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+            Self::Avx2 => false,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Portable => "portable",
+            Self::Avx2 => "avx2",
+        }
+    }
+}
+
+/// `Kernel::detect` で選んだ実装で introsort する。
+pub fn simd_sort(data: &mut [c_int]) {
+    simd_sort_with_kernel(data, Kernel::detect());
+}
+
+/// 実装を指定して introsort する。この CPU で使えない実装を指定すると panic する。
+pub fn simd_sort_with_kernel(data: &mut [c_int], kernel: Kernel) {
+    assert!(
+        kernel.is_available(),
+        "{} kernel is not supported on this CPU",
+        kernel.name()
+    );
+    let recur_limit = 2 * data.len().max(1).ilog2();
+    introsort_internal(data, recur_limit, kernel);
+}
+
+fn introsort_internal(data: &mut [c_int], recur_limit: u32, kernel: Kernel) {
+    let len = data.len();
+
+    if len <= 1 {
+        return;
+    }
+
+    if len < INSERTION_SORT_THRESHOLD {
+        small_sort(data, kernel);
+        return;
+    }
+
+    if recur_limit == 0 {
+        super::heapsort(data);
+        return;
+    }
+
+    let pivot_index = median_of_three(data, 0, len / 2, len - 1);
+    data.swap(pivot_index, len / 2);
+    let pivot = data[len / 2];
+
+    let partition = partition(data, pivot, kernel);
+
+    // 片側が空になったら、pivot と等しい要素を取り除いて先に進める
+    if partition == 0 || partition == len {
+        let equal = super::three_way_partition(data, pivot);
+        let (left, right) = data.split_at_mut(equal.start);
+        introsort_internal(left, recur_limit - 1, kernel);
+        introsort_internal(&mut right[equal.len()..], recur_limit - 1, kernel);
+        return;
+    }
+
+    let (left, right) = data.split_at_mut(partition);
+    introsort_internal(left, recur_limit - 1, kernel);
+    introsort_internal(right, recur_limit - 1, kernel);
+}
+
+/// 戻り値を `p` とすると `data[..p]` は全て `pivot` 以下、`data[p..]` は全て `pivot` 以上になる。
+/// `pivot` は `data` の要素でなければならない。
+fn partition(data: &mut [c_int], pivot: c_int, kernel: Kernel) -> usize {
+    match kernel {
+        Kernel::Portable => unsafe {
+            ffi::block_partition(data.as_mut_ptr(), data.len() as c_int, pivot) as usize
+        },
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        Kernel::Avx2 => unsafe { avx2::partition(data, pivot) },
+        #[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+        Kernel::Avx2 => unreachable!(),
+    }
+}
+
+fn small_sort(data: &mut [c_int], kernel: Kernel) {
+    match kernel {
+        Kernel::Portable => super::small_sort(data),
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        Kernel::Avx2 => unsafe { avx2::small_sort(data) },
+        #[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+        Kernel::Avx2 => unreachable!(),
+    }
+}
+
+/// 呼び出す前に `Kernel::Avx2.is_available()` を確認すること。
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+mod avx2 {
+    #[cfg(target_arch = "x86")]
+    use std::arch::x86::*;
+    #[cfg(target_arch = "x86_64")]
+    use std::arch::x86_64::*;
+    use std::{ffi::c_int, ptr};
+
+    /// 1 つのベクトルに入る `int` の数
+    const LANES: usize = 8;
+
+    /// `mask` のビットが 0 のレーンを前に、1 のレーンを後ろに詰める並べ替え
+    static COMPRESS: [[i32; LANES]; 1 << LANES] = compress_table();
+
+    const fn compress_table() -> [[i32; LANES]; 1 << LANES] {
+        let mut table = [[0; LANES]; 1 << LANES];
+        let mut mask = 0;
+        while mask < 1 << LANES {
+            let mut filled = 0;
+            let mut bit = 0;
+            while bit < 2 {
+                let mut lane = 0;
+                while lane < LANES {
+                    if ((mask >> lane) & 1) == bit {
+                        table[mask][filled] = lane as i32;
+                        filled += 1;
+                    }
+                    lane += 1;
+                }
+                bit += 1;
+            }
+            mask += 1;
+        }
+        table
+    }
+
+    /// https://arxiv.org/abs/1704.08579
+    /// `data[..p]` を `pivot` 以下、`data[p..]` を `pivot` より大きくして `p` を返す。
+    /// 両端の 1 ベクトルずつを退避して空けた場所に、読み込んだベクトルを
+    /// 比較結果で並べ替えて左右の両方に書き込み、それぞれ必要な分だけ進める。
+    /// 空いている場所が少ない側から読むので、書き込みが未読の要素を上書きすることはない。
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn partition(data: &mut [c_int], pivot: c_int) -> usize {
+        let len = data.len();
+        let ptr = data.as_mut_ptr();
+        let mut left_write = 0;
+        let mut right_write = len;
+
+        if len < 2 * LANES {
+            let mut saved = [0; 2 * LANES];
+            ptr::copy_nonoverlapping(ptr, saved.as_mut_ptr(), len);
+            for &value in &saved[..len] {
+                push(ptr, value, pivot, &mut left_write, &mut right_write);
+            }
+            return left_write;
+        }
+
+        let pivots = _mm256_set1_epi32(pivot);
+        let mut saved = [0; 2 * LANES];
+        ptr::copy_nonoverlapping(ptr, saved.as_mut_ptr(), LANES);
+        ptr::copy_nonoverlapping(ptr.add(len - LANES), saved.as_mut_ptr().add(LANES), LANES);
+
+        let mut left_read = LANES;
+        let mut right_read = len - LANES;
+        while right_read - left_read >= LANES {
+            let values = if left_read - left_write <= right_write - right_read {
+                left_read += LANES;
+                _mm256_loadu_si256(ptr.add(left_read - LANES) as *const __m256i)
+            } else {
+                right_read -= LANES;
+                _mm256_loadu_si256(ptr.add(right_read) as *const __m256i)
+            };
+
+            // pivot より大きいレーンのビットが立つ
+            let mask = _mm256_movemask_ps(_mm256_castsi256_ps(_mm256_cmpgt_epi32(values, pivots)))
+                as usize;
+            let permutation = _mm256_loadu_si256(COMPRESS[mask].as_ptr() as *const __m256i);
+            let packed = _mm256_permutevar8x32_epi32(values, permutation);
+            _mm256_storeu_si256(ptr.add(left_write) as *mut __m256i, packed);
+            _mm256_storeu_si256(ptr.add(right_write - LANES) as *mut __m256i, packed);
+
+            let right = mask.count_ones() as usize;
+            left_write += LANES - right;
+            right_write -= right;
+        }
+
+        // 1 ベクトルに満たない残りと、退避しておいた要素を空いた場所に振り分ける
+        let rest = right_read - left_read;
+        let mut rest_values = [0; LANES];
+        ptr::copy_nonoverlapping(ptr.add(left_read), rest_values.as_mut_ptr(), rest);
+        for &value in rest_values[..rest].iter().chain(&saved) {
+            push(ptr, value, pivot, &mut left_write, &mut right_write);
+        }
+
+        left_write
+    }
+
+    #[inline(always)]
+    unsafe fn push(
+        ptr: *mut c_int,
+        value: c_int,
+        pivot: c_int,
+        left_write: &mut usize,
+        right_write: &mut usize,
+    ) {
+        if value > pivot {
+            *right_write -= 1;
+            *ptr.add(*right_write) = value;
+        } else {
+            *ptr.add(*left_write) = value;
+            *left_write += 1;
+        }
+    }
+
+    /// `mysort.c` の `small_sort` と同じく、2 ベクトル分ずつソーティングネットワークで
+    /// ソートしてから、整列済みのブロックを挿入ソートでまとめる。
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn small_sort(data: &mut [c_int]) {
+        for block in data.chunks_mut(2 * LANES) {
+            sort_block(block);
+        }
+        crate::insertion_sort(data);
+    }
+
+    /// 2 * LANES 要素以下の配列を、足りない分を `c_int::MAX` で埋めてソートする。
+    #[target_feature(enable = "avx2")]
+    unsafe fn sort_block(block: &mut [c_int]) {
+        let mut values = [c_int::MAX; 2 * LANES];
+        values[..block.len()].copy_from_slice(block);
+        let ptr = values.as_mut_ptr() as *mut __m256i;
+
+        let low = sort_vector(_mm256_loadu_si256(ptr));
+        if block.len() <= LANES {
+            _mm256_storeu_si256(ptr, low);
+        } else {
+            // 片方を逆順にすると全体が bitonic になるので、
+            // 小さい方の半分と大きい方の半分に分けてからそれぞれをマージする
+            let high = sort_vector(_mm256_loadu_si256(ptr.add(1)));
+            let high = _mm256_permutevar8x32_epi32(high, _mm256_setr_epi32(7, 6, 5, 4, 3, 2, 1, 0));
+            _mm256_storeu_si256(ptr, merge_vector(_mm256_min_epi32(low, high)));
+            _mm256_storeu_si256(ptr.add(1), merge_vector(_mm256_max_epi32(low, high)));
+        }
+
+        block.copy_from_slice(&values[..block.len()]);
+    }
+
+    /// https://en.wikipedia.org/wiki/Bitonic_sorter
+    /// 大きさ `k` の bitonic 列を作る段のうち、距離 `j` のレーンを比較交換するときに
+    /// 大きい方を置くレーンのビットを立てる。
+    const fn bitonic_max_lanes(k: usize, j: usize) -> i32 {
+        let mut mask = 0;
+        let mut lane = 0;
+        while lane < LANES {
+            let ascending = (lane & k) == 0;
+            if (lane > (lane ^ j)) == ascending {
+                mask |= 1 << lane;
+            }
+            lane += 1;
+        }
+        mask
+    }
+
+    /// レーン `i` と `i ^ J` を比較交換し、`MAX` のビットが立つレーンに大きい方を置く。
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn compare_exchange<const J: i32, const MAX: i32>(values: __m256i) -> __m256i {
+        let partners = _mm256_permutevar8x32_epi32(
+            values,
+            _mm256_setr_epi32(J, 1 ^ J, 2 ^ J, 3 ^ J, 4 ^ J, 5 ^ J, 6 ^ J, 7 ^ J),
+        );
+        _mm256_blend_epi32::<MAX>(
+            _mm256_min_epi32(values, partners),
+            _mm256_max_epi32(values, partners),
+        )
+    }
+
+    /// 1 つのベクトルを昇順にソートする。
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn sort_vector(values: __m256i) -> __m256i {
+        let values = compare_exchange::<1, { bitonic_max_lanes(2, 1) }>(values);
+        let values = compare_exchange::<2, { bitonic_max_lanes(4, 2) }>(values);
+        let values = compare_exchange::<1, { bitonic_max_lanes(4, 1) }>(values);
+        merge_vector(values)
+    }
+
+    /// bitonic 列になっている 1 つのベクトルを昇順に並べる。
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn merge_vector(values: __m256i) -> __m256i {
+        let values = compare_exchange::<4, { bitonic_max_lanes(8, 4) }>(values);
+        let values = compare_exchange::<2, { bitonic_max_lanes(8, 2) }>(values);
+        compare_exchange::<1, { bitonic_max_lanes(8, 1) }>(values)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        crate::test::FUZZ_ITERATIONS,
+        pretty_assertions::assert_eq,
+        rand::{seq::SliceRandom, thread_rng, Rng},
+    };
+
+    #[test]
+    fn simd_sort_test() {
+        let mut rng = thread_rng();
+
+        // この CPU で使える実装を全て試す
+        for kernel in Kernel::ALL {
+            if !kernel.is_available() {
+                continue;
+            }
+
+            // fuzz
+            for _ in 0..FUZZ_ITERATIONS {
+                let len = rng.gen_range(0..3000);
+                let max = *[2, 1000, c_int::MAX].choose(&mut rng).unwrap();
+                let mut data = (0..len)
+                    .map(|_| rng.gen_range(0..max))
+                    .collect::<Vec<c_int>>();
+                if rng.gen_bool(0.1) {
+                    data.sort();
+                }
+
+                let mut expected = data.clone();
+                expected.sort();
+
+                simd_sort_with_kernel(&mut data, kernel);
+                assert_eq!(data, expected, "kernel: {}", kernel.name());
+            }
+        }
+
+        let mut data = (0..100_000).rev().collect::<Vec<c_int>>();
+        simd_sort(&mut data);
+        assert!(data.windows(2).all(|w| w[0] <= w[1]));
+    }
+}